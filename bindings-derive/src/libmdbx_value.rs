@@ -36,12 +36,23 @@ pub fn parse(mut item: DeriveInput) -> syn::Result<TokenStream2> {
         .chain(additional_derives)
         .collect::<Vec<_>>();
 
+    // Archived values read from the database are validated, which needs `CheckBytes`
+    let checks_bytes = other_attrs.iter().any(|attr| {
+        attr.path().is_ident("archive")
+            && attr
+                .meta
+                .require_list()
+                .is_ok_and(|list| list.tokens.to_string().contains("check_bytes"))
+    });
+    let check_bytes = (!checks_bytes).then(|| quote!(#[archive(check_bytes)]));
+
     // Restore non-derive attributes
     item.attrs = other_attrs;
 
     // Generate the output
     let output = quote! {
         #[derive(#(#combined_derives),*)]
+        #check_bytes
         #item
     };
 
@@ -0,0 +1,558 @@
+//! Command-line inspector for the databases written with `libmdbx-bindings`.
+//!
+//! The `libmdbx-bindings` binary knows no table types: it prints keys and values as hex, and
+//! decompresses the values that look like zstd frames. Call [`run_with`] from your own binary to
+//! decode the tables of a [`TableSet`] with their registered decoders instead.
+
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    ops::Bound,
+    path::{Path, PathBuf},
+};
+
+use clap::{Parser, Subcommand};
+use eyre::{WrapErr, bail, eyre};
+use libmdbx_bindings::{TableRegistry, TableSet};
+use libmdbx_native::{
+    CheckError, CheckOptions, DatabaseFlags, Environment, EnvironmentFlags,
+    MaxReadTransactionDuration, Mode, WriteFlags,
+};
+
+/// Maximum number of named databases that can be opened.
+const MAX_DBS: usize = 1024;
+
+/// Name of the data file of an environment directory.
+const DATA_FILE: &str = "mdbx.dat";
+
+/// Magic number starting every zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "libmdbx-bindings",
+    version,
+    about = "Inspects libmdbx databases"
+)]
+struct Cli {
+    /// Path to the database directory.
+    path: PathBuf,
+    /// Opens the database in read/write mode, which only `load` needs.
+    #[arg(long, global = true)]
+    write: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Prints the statistics of the environment.
+    Stat,
+    /// Lists the tables with their flags and number of entries.
+    List,
+    /// Prints the row stored under a hex encoded key.
+    Get { table: String, key: String },
+    /// Prints the rows of a table in key order.
+    Scan {
+        table: String,
+        /// Hex encoded key range such as `00..ff`, `00..=ff`, `00..` or `..ff`.
+        #[arg(long)]
+        range: Option<String>,
+        /// Maximum number of rows to print.
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Writes every entry of a table to stdout as hex `key value` lines.
+    Dump { table: String },
+    /// Reads hex `key value` lines written by `dump` from stdin into a table.
+    Load {
+        table: String,
+        /// Creates the table as a DUPSORT table if it doesn't exist yet.
+        #[arg(long)]
+        dupsort: bool,
+    },
+    /// Copies the environment into a new directory.
+    Copy {
+        /// Directory of the copy, created if needed. It must not hold a data file yet.
+        dest: PathBuf,
+        /// Omits free pages from the copy.
+        #[arg(long)]
+        compact: bool,
+    },
+    /// Walks every table, checking the ordering of the entries, that they decode and that the
+    /// counts match the statistics.
+    Check,
+    /// Lists the slots of the reader lock table.
+    Readers {
+        /// Clears the slots held by processes that no longer exist first.
+        #[arg(long)]
+        clear_stale: bool,
+    },
+}
+
+/// Runs the inspector on the command-line arguments, printing keys and values as hex.
+pub fn run() -> eyre::Result<()> {
+    execute(Cli::parse(), &TableRegistry::default())
+}
+
+/// Runs the inspector on the command-line arguments, decoding the tables of `S` with the
+/// decoders they registered.
+pub fn run_with<S: TableSet>() -> eyre::Result<()> {
+    let mut registry = TableRegistry::default();
+    S::register(&mut registry);
+
+    execute(Cli::parse(), &registry)
+}
+
+fn execute(cli: Cli, registry: &TableRegistry) -> eyre::Result<()> {
+    let env = open(&cli.path, cli.write)?;
+    let printer = Printer { registry };
+    let mut out = io::stdout().lock();
+
+    match cli.command {
+        Command::Stat => stat(&env, &mut out),
+        Command::List => list(&env, &mut out),
+        Command::Get { table, key } => get(&env, &printer, &table, &key, &mut out),
+        Command::Scan {
+            table,
+            range,
+            limit,
+        } => scan(&env, &printer, &table, range.as_deref(), limit, &mut out),
+        Command::Dump { table } => dump(&env, &table, &mut out),
+        Command::Load { table, dupsort } => {
+            if !cli.write {
+                bail!("`load` writes to the database, run it with --write");
+            }
+            load(&env, &table, dupsort, io::stdin().lock(), &mut out)
+        }
+        Command::Copy { dest, compact } => copy(&env, &dest, compact),
+        Command::Check => check(&env, &printer, &mut out),
+        Command::Readers { clear_stale } => readers(&env, clear_stale, &mut out),
+    }
+}
+
+/// Opens the environment, read-only unless `write` is set so that it is safe to inspect a
+/// database used by a live writer.
+fn open(path: &Path, write: bool) -> eyre::Result<Environment> {
+    let mut builder = Environment::builder();
+    builder
+        .set_max_dbs(MAX_DBS)
+        .set_max_read_transaction_duration(MaxReadTransactionDuration::Unbounded);
+    if !write {
+        builder.set_flags(EnvironmentFlags::from(Mode::ReadOnly));
+    }
+
+    builder
+        .open(path)
+        .wrap_err_with(|| format!("could not open the database at {}", path.display()))
+}
+
+fn stat(env: &Environment, out: &mut impl Write) -> eyre::Result<()> {
+    let stat = env.stat()?;
+    let info = env.info()?;
+
+    writeln!(out, "page size:           {}", stat.page_size())?;
+    writeln!(out, "depth:               {}", stat.depth())?;
+    writeln!(out, "branch pages:        {}", stat.branch_pages())?;
+    writeln!(out, "leaf pages:          {}", stat.leaf_pages())?;
+    writeln!(out, "overflow pages:      {}", stat.overflow_pages())?;
+    writeln!(out, "entries:             {}", stat.entries())?;
+    writeln!(out, "map size:            {}", info.map_size())?;
+    writeln!(out, "last page number:    {}", info.last_pgno())?;
+    writeln!(out, "last transaction id: {}", info.last_txnid())?;
+    writeln!(out, "free pages:          {}", env.freelist()?)?;
+
+    Ok(())
+}
+
+fn list(env: &Environment, out: &mut impl Write) -> eyre::Result<()> {
+    for db in env.list_dbs()? {
+        writeln!(
+            out,
+            "{}\t{:?}\t{} entries",
+            db.name(),
+            db.flags(),
+            db.stat().entries()
+        )?;
+    }
+
+    Ok(())
+}
+
+fn get(
+    env: &Environment,
+    printer: &Printer<'_>,
+    table: &str,
+    key: &str,
+    out: &mut impl Write,
+) -> eyre::Result<()> {
+    let key = parse_hex(key)?;
+    let txn = env.begin_ro_txn()?;
+    let db = txn.open_db(Some(table))?;
+
+    let Some(value) = txn.get::<Vec<u8>>(db.dbi(), &key)? else {
+        bail!("no row stored under {} in {table}", hex(&key));
+    };
+    writeln!(out, "{}", printer.row(table, &key, &value))?;
+
+    Ok(())
+}
+
+fn scan(
+    env: &Environment,
+    printer: &Printer<'_>,
+    table: &str,
+    range: Option<&str>,
+    limit: Option<usize>,
+    out: &mut impl Write,
+) -> eyre::Result<()> {
+    let (start, end) = match range {
+        Some(range) => parse_range(range)?,
+        None => (None, Bound::Unbounded),
+    };
+
+    let txn = env.begin_ro_txn()?;
+    let db = txn.open_db(Some(table))?;
+    let mut cursor = txn.cursor(&db)?;
+
+    let mut row = match &start {
+        Some(start) => cursor.set_range::<Vec<u8>, Vec<u8>>(start)?,
+        None => cursor.first::<Vec<u8>, Vec<u8>>()?,
+    };
+
+    for _ in 0..limit.unwrap_or(usize::MAX) {
+        let Some((key, value)) = row else {
+            break;
+        };
+
+        // Compared with the comparator of the table, the bytes of `INTEGER_KEY` keys are not ordered.
+        let past_end = match &end {
+            Bound::Included(end) => txn.compare_keys(db.dbi(), &key, end)?.is_gt(),
+            Bound::Excluded(end) => txn.compare_keys(db.dbi(), &key, end)?.is_ge(),
+            Bound::Unbounded => false,
+        };
+        if past_end {
+            break;
+        }
+
+        writeln!(out, "{}", printer.row(table, &key, &value))?;
+        row = cursor.next()?;
+    }
+
+    Ok(())
+}
+
+fn dump(env: &Environment, table: &str, out: &mut impl Write) -> eyre::Result<()> {
+    let txn = env.begin_ro_txn()?;
+    let db = txn.open_db(Some(table))?;
+    let cursor = txn.cursor(&db)?;
+
+    for row in cursor.iter_slices() {
+        let (key, value) = row?;
+        writeln!(out, "{} {}", hex(&key), hex(&value))?;
+    }
+
+    Ok(())
+}
+
+fn load(
+    env: &Environment,
+    table: &str,
+    dupsort: bool,
+    input: impl BufRead,
+    out: &mut impl Write,
+) -> eyre::Result<()> {
+    let flags = if dupsort {
+        DatabaseFlags::DUP_SORT
+    } else {
+        DatabaseFlags::empty()
+    };
+
+    let txn = env.begin_rw_txn()?;
+    let db = txn.create_db(Some(table), flags)?;
+
+    let mut loaded = 0;
+    for (number, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (key, value) = line
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| eyre!("line {}: expected `key value`", number + 1))?;
+        let key = parse_hex(key).wrap_err_with(|| format!("line {}", number + 1))?;
+        let value = parse_hex(value).wrap_err_with(|| format!("line {}", number + 1))?;
+
+        txn.put(db.dbi(), key, value, WriteFlags::UPSERT)?;
+        loaded += 1;
+    }
+
+    txn.commit()?;
+    writeln!(out, "loaded {loaded} entries into {table}")?;
+
+    Ok(())
+}
+
+fn copy(env: &Environment, dest: &Path, compact: bool) -> eyre::Result<()> {
+    fs::create_dir_all(dest).wrap_err_with(|| format!("could not create {}", dest.display()))?;
+
+    let file = dest.join(DATA_FILE);
+    env.copy(&file, compact)
+        .wrap_err_with(|| format!("could not copy the database to {}", file.display()))?;
+
+    Ok(())
+}
+
+fn check(env: &Environment, printer: &Printer<'_>, out: &mut impl Write) -> eyre::Result<()> {
+    let options = CheckOptions::default().with_validator(|table, key, value| {
+        match printer.registry.format_row(table, key, value) {
+            Some(Err(err)) => Err(err.to_string()),
+            _ => Ok(()),
+        }
+    });
+    let report = env.check(&options)?;
+
+    for table in &report.tables {
+        if table.errors.is_empty() {
+            writeln!(out, "{}: ok, {} entries", table.name, table.entries)?;
+            continue;
+        }
+
+        writeln!(out, "{}: {} entries", table.name, table.entries)?;
+        for error in &table.errors {
+            writeln!(out, "  {}", describe(error))?;
+        }
+        if table.truncated {
+            writeln!(out, "  more errors were dropped")?;
+        }
+    }
+    for error in &report.errors {
+        writeln!(out, "environment: {}", describe(error))?;
+    }
+
+    if !report.is_ok() {
+        bail!("the database failed the check");
+    }
+
+    Ok(())
+}
+
+fn describe(error: &CheckError) -> String {
+    match error {
+        CheckError::KeyOrder { key } => format!("key {} is out of order", hex(key)),
+        CheckError::DupOrder { key, value } => {
+            format!(
+                "duplicate {} of key {} is out of order",
+                hex(value),
+                hex(key)
+            )
+        }
+        CheckError::Invalid { key, reason } => format!("row {} is invalid: {reason}", hex(key)),
+        CheckError::Read(err) => format!("walk stopped early: {err}"),
+        CheckError::EntryCount { expected, found } => {
+            format!("read {found} entries but the statistics count {expected}")
+        }
+        CheckError::PageCount { used, allocated } => {
+            format!("{used} pages are used but only {allocated} are allocated")
+        }
+    }
+}
+
+fn readers(env: &Environment, clear_stale: bool, out: &mut impl Write) -> eyre::Result<()> {
+    if clear_stale {
+        writeln!(out, "cleared {} stale reader slots", env.reader_check()?)?;
+    }
+
+    let info = env.info()?;
+    writeln!(
+        out,
+        "reader slots in use: {}/{}",
+        info.num_readers(),
+        info.max_readers()
+    )?;
+
+    for reader in env.readers()? {
+        writeln!(
+            out,
+            "slot {}: pid {} thread {:#x} txn {} lag {} retained {} bytes",
+            reader.slot(),
+            reader.pid(),
+            reader.thread(),
+            reader.txnid(),
+            reader.lag(),
+            reader.bytes_retained()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Formats rows with the decoders of the registry, falling back to hex.
+struct Printer<'a> {
+    registry: &'a TableRegistry,
+}
+
+impl Printer<'_> {
+    fn row(&self, table: &str, key: &[u8], value: &[u8]) -> String {
+        match self.registry.format_row(table, key, value) {
+            Some(Ok(row)) => row,
+            Some(Err(err)) => format!("{} => {} (failed to decode: {err})", hex(key), hex(value)),
+            None => format!("{} => {}", hex(key), raw_value(value)),
+        }
+    }
+}
+
+/// Formats a value of an unknown type, decompressing it first if it is a zstd frame.
+fn raw_value(value: &[u8]) -> String {
+    match value
+        .starts_with(&ZSTD_MAGIC)
+        .then(|| zstd::decode_all(value))
+    {
+        Some(Ok(decompressed)) => format!("zstd {}", hex(&decompressed)),
+        _ => hex(value),
+    }
+}
+
+/// Formats bytes as a `0x` prefixed hex string.
+fn hex(bytes: &[u8]) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::with_capacity(2 + bytes.len() * 2);
+    out.push_str("0x");
+    for byte in bytes {
+        let _ = write!(out, "{byte:02x}");
+    }
+
+    out
+}
+
+/// Parses a hex string, with or without a `0x` prefix.
+fn parse_hex(hex: &str) -> eyre::Result<Vec<u8>> {
+    let digits = hex.strip_prefix("0x").unwrap_or(hex);
+    if !digits.is_ascii() || digits.len() % 2 != 0 {
+        bail!("{hex} is not a hex string");
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .wrap_err_with(|| format!("{hex} is not a hex string"))
+        })
+        .collect()
+}
+
+/// Parses a `start..end` or `start..=end` range of hex keys, where both ends are optional.
+fn parse_range(range: &str) -> eyre::Result<(Option<Vec<u8>>, Bound<Vec<u8>>)> {
+    let (start, end) = range
+        .split_once("..")
+        .ok_or_else(|| eyre!("expected a range such as `00..ff`, got {range}"))?;
+
+    let start = (!start.is_empty()).then(|| parse_hex(start)).transpose()?;
+    let end = match end.strip_prefix('=') {
+        Some(end) => Bound::Included(parse_hex(end)?),
+        None if end.is_empty() => Bound::Unbounded,
+        None => Bound::Excluded(parse_hex(end)?),
+    };
+
+    Ok((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::*;
+
+    /// Runs the inspector with the given arguments, the database path included.
+    fn run_args(args: &[&OsStr]) -> eyre::Result<()> {
+        let cli = Cli::try_parse_from([OsStr::new("libmdbx-bindings")].iter().chain(args))?;
+        execute(cli, &TableRegistry::default())
+    }
+
+    #[test]
+    fn copy_compact() {
+        let source = tempfile::tempdir().unwrap();
+        {
+            let env = open(source.path(), true).unwrap();
+            let txn = env.begin_rw_txn().unwrap();
+            let db = txn.create_db(Some("Rows"), DatabaseFlags::empty()).unwrap();
+            for key in 0u64..1000 {
+                txn.put(db.dbi(), key.to_be_bytes(), [0; 64], WriteFlags::UPSERT)
+                    .unwrap();
+            }
+            txn.commit().unwrap();
+
+            // Free pages for the compacting copy to omit.
+            let txn = env.begin_rw_txn().unwrap();
+            let db = txn.open_db(Some("Rows")).unwrap();
+            for key in 0u64..500 {
+                txn.del(db.dbi(), key.to_be_bytes(), None).unwrap();
+            }
+            txn.commit().unwrap();
+        }
+
+        let dest = tempfile::tempdir().unwrap();
+        let copy = dest.path().join("copy");
+        let args = [
+            source.path().as_os_str(),
+            OsStr::new("copy"),
+            copy.as_os_str(),
+            OsStr::new("--compact"),
+        ];
+        run_args(&args).unwrap();
+
+        let env = open(&copy, false).unwrap();
+        let txn = env.begin_ro_txn().unwrap();
+        let db = txn.open_db(Some("Rows")).unwrap();
+        assert_eq!(txn.db_stat(&db).unwrap().entries(), 500);
+        assert_eq!(
+            txn.get::<Vec<u8>>(db.dbi(), &999u64.to_be_bytes()).unwrap(),
+            Some(vec![0; 64])
+        );
+        drop(txn);
+        assert_eq!(env.freelist().unwrap(), 0);
+
+        // The data file of the first copy is never overwritten.
+        assert!(run_args(&args).is_err());
+    }
+
+    #[test]
+    fn scan_integer_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = open(dir.path(), true).unwrap();
+        let txn = env.begin_rw_txn().unwrap();
+        let db = txn
+            .create_db(Some("Numbers"), DatabaseFlags::INTEGER_KEY)
+            .unwrap();
+        for key in 0u64..600 {
+            txn.put(db.dbi(), key.to_ne_bytes(), [0], WriteFlags::UPSERT)
+                .unwrap();
+        }
+        txn.commit().unwrap();
+
+        let registry = TableRegistry::default();
+        let printer = Printer {
+            registry: &registry,
+        };
+        let scanned = |range: String| {
+            let mut out = Vec::new();
+            scan(&env, &printer, "Numbers", Some(&range), None, &mut out).unwrap();
+            String::from_utf8(out)
+                .unwrap()
+                .lines()
+                .map(|line| line.split_once(" => ").unwrap().0.to_string())
+                .collect::<Vec<_>>()
+        };
+        let keys = |keys: std::ops::Range<u64>| -> Vec<_> {
+            keys.map(|key| hex(&key.to_ne_bytes())).collect()
+        };
+
+        let (start, end) = (hex(&100u64.to_ne_bytes()), hex(&300u64.to_ne_bytes()));
+        assert_eq!(scanned(format!("{start}..{end}")), keys(100..300));
+        assert_eq!(scanned(format!("{start}..={end}")), keys(100..301));
+        assert_eq!(scanned(format!("..{start}")), keys(0..100));
+    }
+}
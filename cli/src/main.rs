@@ -0,0 +1,3 @@
+fn main() -> eyre::Result<()> {
+    libmdbx_bindings_cli::run()
+}
@@ -0,0 +1,344 @@
+use std::{borrow::Cow, cmp::Ordering, fmt};
+
+use byteorder::{ByteOrder, NativeEndian};
+
+use crate::{
+    database::Database,
+    environment::{list_dbs_in, DatabaseInfo},
+    error::{Error, Result},
+    flags::DatabaseFlags,
+    transaction::RO,
+    Environment, Stat, Transaction,
+};
+
+/// Number of meta pages at the start of every MDBX file.
+const NUM_METAS: usize = 3;
+
+/// Validates a `(key, value)` pair of the named database, returning why it is invalid.
+pub type CheckValidator<'a> = dyn Fn(&str, &[u8], &[u8]) -> std::result::Result<(), String> + 'a;
+
+/// Options of [`Environment::check()`].
+pub struct CheckOptions<'a> {
+    /// Called for every entry, typically to check that values decode with their codec.
+    validator: Option<Box<CheckValidator<'a>>>,
+    /// Maximum number of errors recorded per database.
+    max_errors: usize,
+    /// Names of the databases to walk, all of them if [None].
+    tables: Option<Vec<String>>,
+}
+
+impl Default for CheckOptions<'_> {
+    fn default() -> Self {
+        Self {
+            validator: None,
+            max_errors: 100,
+            tables: None,
+        }
+    }
+}
+
+impl fmt::Debug for CheckOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CheckOptions")
+            .field("validator", &self.validator.is_some())
+            .field("max_errors", &self.max_errors)
+            .field("tables", &self.tables)
+            .finish()
+    }
+}
+
+impl<'a> CheckOptions<'a> {
+    /// Sets the function validating every `(key, value)` pair.
+    pub fn with_validator(
+        mut self,
+        validator: impl Fn(&str, &[u8], &[u8]) -> std::result::Result<(), String> + 'a,
+    ) -> Self {
+        self.validator = Some(Box::new(validator));
+        self
+    }
+
+    /// Sets the maximum number of errors recorded per database before the rest are dropped.
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Restricts the walk to the given databases. Page accounting still covers all of them.
+    pub fn with_tables(mut self, tables: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.tables = Some(tables.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+/// A problem found by [`Environment::check()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckError {
+    /// The key is not greater than the key before it.
+    KeyOrder { key: Vec<u8> },
+    /// The duplicate is not greater than the duplicate before it.
+    DupOrder { key: Vec<u8>, value: Vec<u8> },
+    /// The validator rejected the entry.
+    Invalid { key: Vec<u8>, reason: String },
+    /// The cursor failed to move, the rest of the database was not walked.
+    Read(Error),
+    /// The number of entries walked differs from the database statistics.
+    EntryCount { expected: usize, found: usize },
+    /// The pages used by the trees and the free list exceed the pages allocated in the file.
+    PageCount { used: usize, allocated: usize },
+}
+
+/// Result of checking a single database.
+#[derive(Debug)]
+pub struct TableReport {
+    /// Name of the database.
+    pub name: String,
+    /// Number of entries walked.
+    pub entries: usize,
+    /// Problems found, at most [`CheckOptions::with_max_errors`] of them.
+    pub errors: Vec<CheckError>,
+    /// Whether errors were dropped because the maximum was reached.
+    pub truncated: bool,
+}
+
+impl TableReport {
+    fn push(&mut self, error: CheckError, max_errors: usize) {
+        if self.errors.len() < max_errors {
+            self.errors.push(error);
+        } else {
+            self.truncated = true;
+        }
+    }
+}
+
+/// Result of [`Environment::check()`].
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    /// Reports of the walked databases.
+    pub tables: Vec<TableReport>,
+    /// Problems found at the environment level.
+    pub errors: Vec<CheckError>,
+}
+
+impl CheckReport {
+    /// Returns `true` if no problem was found.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty() && self.tables.iter().all(|table| table.errors.is_empty())
+    }
+}
+
+impl Environment {
+    /// Checks the integrity of the named databases within a single read transaction.
+    ///
+    /// Every database is walked with a cursor, verifying the ordering of keys and duplicates
+    /// according to the database flags, running the validator of `options` on every entry and
+    /// comparing the number of entries with [`Stat`]. The pages of all the trees and the free
+    /// list must fit in the pages allocated in the file.
+    ///
+    /// Problems are collected in the returned report, an error means the check could not run.
+    pub fn check(&self, options: &CheckOptions<'_>) -> Result<CheckReport> {
+        let txn = self.begin_ro_txn()?;
+        let mut report = CheckReport::default();
+
+        let main = txn.open_db(None)?;
+        let gc = Database::freelist_db();
+        let mut used = NUM_METAS + pages(&txn.db_stat(&main)?) + pages(&txn.db_stat(&gc)?);
+        used += free_pages(&txn, &gc)?;
+
+        for db in list_dbs_in(&txn)? {
+            used += pages(db.stat());
+
+            if options
+                .tables
+                .as_ref()
+                .is_some_and(|tables| !tables.iter().any(|name| name == db.name()))
+            {
+                continue;
+            }
+
+            report.tables.push(check_db(&txn, &db, options)?);
+        }
+
+        let allocated = self.info()?.last_pgno() + 1;
+        if used > allocated {
+            report
+                .errors
+                .push(CheckError::PageCount { used, allocated });
+        }
+
+        Ok(report)
+    }
+}
+
+/// Walks a database, checking every entry.
+fn check_db(
+    txn: &Transaction<RO>,
+    db: &DatabaseInfo,
+    options: &CheckOptions<'_>,
+) -> Result<TableReport> {
+    let handle = txn.open_db(Some(db.name()))?;
+    let mut cursor = txn.cursor(&handle)?;
+
+    let flags = db.flags();
+    let dupsort = flags.contains(DatabaseFlags::DUP_SORT);
+    let integer_key = flags.contains(DatabaseFlags::INTEGER_KEY);
+    let reverse_key = flags.contains(DatabaseFlags::REVERSE_KEY);
+    let integer_dup = flags.contains(DatabaseFlags::INTEGER_DUP);
+    let reverse_dup = flags.contains(DatabaseFlags::REVERSE_DUP);
+
+    let mut report = TableReport {
+        name: db.name().to_string(),
+        entries: 0,
+        errors: Vec::new(),
+        truncated: false,
+    };
+    let mut prev: Option<(Vec<u8>, Vec<u8>)> = None;
+    let mut row = cursor.first::<Cow<'_, [u8]>, Cow<'_, [u8]>>();
+
+    loop {
+        let (key, value) = match row {
+            Ok(Some(row)) => row,
+            Ok(None) => break,
+            Err(err) => {
+                report.push(CheckError::Read(err), options.max_errors);
+                break;
+            }
+        };
+        report.entries += 1;
+
+        if let Some((prev_key, prev_value)) = &prev {
+            match compare(prev_key, &key, integer_key, reverse_key) {
+                Ordering::Less => (),
+                Ordering::Equal if dupsort => {
+                    if compare(prev_value, &value, integer_dup, reverse_dup) != Ordering::Less {
+                        let error = CheckError::DupOrder {
+                            key: key.to_vec(),
+                            value: value.to_vec(),
+                        };
+                        report.push(error, options.max_errors);
+                    }
+                }
+                _ => report.push(
+                    CheckError::KeyOrder { key: key.to_vec() },
+                    options.max_errors,
+                ),
+            }
+        }
+
+        if let Some(validator) = &options.validator {
+            if let Err(reason) = validator(db.name(), &key, &value) {
+                report.push(
+                    CheckError::Invalid {
+                        key: key.to_vec(),
+                        reason,
+                    },
+                    options.max_errors,
+                );
+            }
+        }
+
+        prev = Some((key.into_owned(), value.into_owned()));
+        row = cursor.next();
+    }
+
+    if report.entries != db.stat().entries() {
+        let error = CheckError::EntryCount {
+            expected: db.stat().entries(),
+            found: report.entries,
+        };
+        report.push(error, options.max_errors);
+    }
+
+    Ok(report)
+}
+
+/// Compares two keys or duplicates the way MDBX orders them for the given flags.
+fn compare(a: &[u8], b: &[u8], integer: bool, reverse: bool) -> Ordering {
+    if integer && a.len() == b.len() {
+        match a.len() {
+            4 => return NativeEndian::read_u32(a).cmp(&NativeEndian::read_u32(b)),
+            8 => return NativeEndian::read_u64(a).cmp(&NativeEndian::read_u64(b)),
+            _ => (),
+        }
+    }
+
+    if reverse {
+        a.iter().rev().cmp(b.iter().rev())
+    } else {
+        a.cmp(b)
+    }
+}
+
+/// Total number of pages of a tree.
+fn pages(stat: &Stat) -> usize {
+    stat.branch_pages() + stat.leaf_pages() + stat.overflow_pages()
+}
+
+/// Number of pages listed in the free list, see [`Environment::freelist()`].
+fn free_pages(txn: &Transaction<RO>, gc: &Database) -> Result<usize> {
+    let cursor = txn.cursor(gc)?;
+
+    let mut free = 0;
+    for result in cursor.iter_slices() {
+        let (_key, value) = result?;
+        if value.len() < std::mem::size_of::<u32>() {
+            return Err(Error::Corrupted);
+        }
+
+        free += NativeEndian::read_u32(&value) as usize;
+    }
+
+    Ok(free)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CheckError, CheckOptions, DatabaseFlags, Environment, WriteFlags};
+
+    #[test]
+    fn test_check() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let env = Environment::builder()
+            .set_max_dbs(4)
+            .open(tempdir.path())
+            .unwrap();
+
+        {
+            let tx = env.begin_rw_txn().unwrap();
+            let plain = tx.create_db(Some("plain"), DatabaseFlags::empty()).unwrap();
+            let dup = tx.create_db(Some("dup"), DatabaseFlags::DUP_SORT).unwrap();
+            for i in 0u8..100 {
+                tx.put(plain.dbi(), [i], [i], WriteFlags::empty()).unwrap();
+                tx.put(dup.dbi(), [i / 10], [i], WriteFlags::empty())
+                    .unwrap();
+            }
+            tx.commit().unwrap();
+        }
+
+        let report = env.check(&CheckOptions::default()).unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        assert_eq!(report.tables.len(), 2);
+        assert!(report.tables.iter().all(|table| table.entries == 100));
+
+        let options = CheckOptions::default()
+            .with_tables(["plain"])
+            .with_max_errors(3)
+            .with_validator(|_, key, _| {
+                if key[0] % 2 == 0 {
+                    Ok(())
+                } else {
+                    Err("odd".to_string())
+                }
+            });
+        let report = env.check(&options).unwrap();
+        assert_eq!(report.tables.len(), 1);
+        assert_eq!(report.tables[0].errors.len(), 3);
+        assert!(report.tables[0].truncated);
+        assert_eq!(
+            report.tables[0].errors[0],
+            CheckError::Invalid {
+                key: vec![1],
+                reason: "odd".to_string()
+            }
+        );
+    }
+}
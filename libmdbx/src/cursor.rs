@@ -50,6 +50,32 @@ where
         }
     }
 
+    /// Returns the transaction the cursor belongs to.
+    pub fn txn(&self) -> &Transaction<K> {
+        &self.txn
+    }
+
+    /// Binds the cursor again to its transaction with `mdbx_cursor_renew`, after the transaction
+    /// was renewed. The cursor is unpositioned afterwards.
+    pub fn renew(&mut self) -> Result<()> {
+        self.txn
+            .txn_execute(|txn| unsafe { mdbx_result(ffi::mdbx_cursor_renew(txn, self.cursor)) })??;
+
+        Ok(())
+    }
+
+    /// Estimates the number of items between this cursor and `other` with
+    /// `mdbx_estimate_distance`, negative if `other` is positioned before this cursor.
+    ///
+    /// Both cursors must be positioned on the same database.
+    pub fn estimate_distance(&self, other: &Self) -> Result<isize> {
+        let mut distance: isize = 0;
+        self.txn
+            .txn_execute(|_| unsafe { mdbx_result(ffi::mdbx_estimate_distance(self.cursor, other.cursor, &mut distance)) })??;
+
+        Ok(distance)
+    }
+
     /// Returns a raw pointer to the underlying MDBX cursor.
     ///
     /// The caller **must** ensure that the pointer is not used after the
@@ -434,6 +460,30 @@ impl Cursor<RW> {
         Ok(())
     }
 
+    /// Stores the duplicates of `key` held back to back in `values`, `value_size` bytes each, in a
+    /// single call with [WriteFlags::MULTIPLE]. The database must be opened with
+    /// [DatabaseFlags::DUP_FIXED].
+    ///
+    /// Returns the number of stored duplicates.
+    pub fn put_multiple(&mut self, key: &[u8], values: &[u8], value_size: usize, flags: WriteFlags) -> Result<usize> {
+        if value_size == 0 || values.len() % value_size != 0 {
+            return Err(Error::BadValSize);
+        }
+
+        let key_val: ffi::MDBX_val = ffi::MDBX_val { iov_len: key.len(), iov_base: key.as_ptr() as *mut c_void };
+        let mut data_val: [ffi::MDBX_val; 2] = [
+            ffi::MDBX_val { iov_len: value_size, iov_base: values.as_ptr() as *mut c_void },
+            ffi::MDBX_val { iov_len: values.len() / value_size, iov_base: ptr::null_mut() }
+        ];
+        mdbx_result(unsafe {
+            self.txn.txn_execute(|_| {
+                ffi::mdbx_cursor_put(self.cursor, &key_val, data_val.as_mut_ptr(), (flags | WriteFlags::MULTIPLE).bits())
+            })?
+        })?;
+
+        Ok(data_val[1].iov_len)
+    }
+
     /// Deletes the current key/data pair.
     ///
     /// ### Flags
@@ -1,15 +1,15 @@
 use crate::{
     database::Database,
     error::{mdbx_result, Error, Result},
-    flags::EnvironmentFlags,
-    transaction::{RO, RW},
+    flags::{DatabaseFlags, EnvironmentFlags},
+    transaction::{Canary, RO, RW},
     txn_manager::{TxnManager, TxnManagerMessage, TxnPtr},
     Mode, SyncMode, Transaction, TransactionKind,
 };
 use byteorder::{ByteOrder, NativeEndian};
 use mem::size_of;
 use std::{
-    ffi::CString,
+    ffi::{c_int, c_void, CString},
     fmt::{self, Debug},
     mem,
     ops::{Bound, RangeBounds},
@@ -17,10 +17,13 @@ use std::{
     ptr,
     sync::{mpsc::sync_channel, Arc},
     thread::sleep,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tracing::warn;
 
+/// Interval between two attempts of [`Environment::begin_rw_txn_timeout`] to take the write lock.
+const RW_TXN_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
 /// The default maximum duration of a read transaction.
 #[cfg(feature = "read-tx-timeouts")]
 const DEFAULT_MAX_READ_TRANSACTION_DURATION: Duration = Duration::from_secs(5 * 60);
@@ -108,13 +111,7 @@ impl Environment {
     pub fn begin_rw_txn(&self) -> Result<Transaction<RW>> {
         let mut warned = false;
         let txn = loop {
-            let (tx, rx) = sync_channel(0);
-            self.txn_manager().send_message(TxnManagerMessage::Begin {
-                parent: TxnPtr(ptr::null_mut()),
-                flags: RW::OPEN_FLAGS,
-                sender: tx,
-            });
-            let res = rx.recv().unwrap();
+            let res = self.begin_rw_txn_with_flags(RW::OPEN_FLAGS);
             if matches!(&res, Err(Error::Busy)) {
                 if !warned {
                     warned = true;
@@ -129,6 +126,43 @@ impl Environment {
         Ok(Transaction::new_from_ptr(self.clone(), txn.0))
     }
 
+    /// Create a read-write transaction for use with the environment, returning [`Error::Busy`]
+    /// immediately if another read-write transaction is open on the environment, in this process
+    /// or another one.
+    pub fn try_begin_rw_txn(&self) -> Result<Transaction<RW>> {
+        let txn = self.begin_rw_txn_with_flags(RW::OPEN_FLAGS | ffi::MDBX_TXN_TRY)?;
+        Ok(Transaction::new_from_ptr(self.clone(), txn.0))
+    }
+
+    /// Create a read-write transaction for use with the environment, waiting at most `timeout`
+    /// for the other read-write transactions to finish before returning [`Error::Busy`].
+    pub fn begin_rw_txn_timeout(&self, timeout: Duration) -> Result<Transaction<RW>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.try_begin_rw_txn() {
+                Err(Error::Busy) => (),
+                res => return res,
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::Busy);
+            }
+            sleep(remaining.min(RW_TXN_RETRY_INTERVAL));
+        }
+    }
+
+    /// Begins a read-write transaction with `flags` on the transaction manager thread.
+    fn begin_rw_txn_with_flags(&self, flags: ffi::MDBX_txn_flags_t) -> Result<TxnPtr> {
+        let (tx, rx) = sync_channel(0);
+        self.txn_manager().send_message(TxnManagerMessage::Begin {
+            parent: TxnPtr(ptr::null_mut()),
+            flags,
+            sender: tx,
+        });
+        rx.recv().unwrap()
+    }
+
     /// Returns a raw pointer to the underlying MDBX environment.
     ///
     /// The caller **must** ensure that the pointer is never dereferenced after the environment has
@@ -185,6 +219,12 @@ impl Environment {
         }
     }
 
+    /// Reads the canary stored by the last committed transaction, see
+    /// [`Transaction::put_canary`](crate::Transaction::put_canary).
+    pub fn canary(&self) -> Result<Canary> {
+        self.begin_ro_txn()?.canary()
+    }
+
     /// Retrieves the total number of pages on the freelist.
     ///
     /// Along with [`Environment::info()`], this can be used to calculate the exact number
@@ -228,6 +268,194 @@ impl Environment {
 
         Ok(freelist)
     }
+
+    /// Copies the environment to the data file `dest`, which must not exist yet. Its parent
+    /// directory must exist, and the copy opens as an environment directory when the file is
+    /// named `mdbx.dat`.
+    ///
+    /// With `compact`, free pages are omitted and the remaining pages are renumbered
+    /// sequentially. The copy runs in a read transaction, so it is safe against a live writer.
+    pub fn copy(&self, dest: &Path, compact: bool) -> Result<()> {
+        let dest = CString::new(path_to_bytes(dest)).map_err(|_| Error::Invalid)?;
+        let flags = if compact {
+            ffi::MDBX_CP_COMPACT
+        } else {
+            ffi::MDBX_CP_DEFAULTS
+        };
+
+        mdbx_result(unsafe { ffi::mdbx_env_copy(self.env_ptr(), dest.as_ptr(), flags) })?;
+
+        Ok(())
+    }
+
+    /// Lists the slots of the reader lock table used by the processes sharing this environment.
+    pub fn readers(&self) -> Result<Vec<ReaderInfo>> {
+        let mut readers = Vec::<ReaderInfo>::new();
+        mdbx_result(unsafe {
+            ffi::mdbx_reader_list(
+                self.env_ptr(),
+                Some(collect_reader),
+                &mut readers as *mut Vec<ReaderInfo> as *mut c_void,
+            )
+        })?;
+
+        Ok(readers)
+    }
+
+    /// Clears the reader slots held by processes that no longer exist, returning the number of
+    /// cleared slots.
+    pub fn reader_check(&self) -> Result<usize> {
+        let mut dead: c_int = 0;
+        mdbx_result(unsafe { ffi::mdbx_reader_check(self.env_ptr(), &mut dead) })?;
+
+        Ok(dead as usize)
+    }
+
+    /// Lists the named databases stored in the main database of this environment, along with
+    /// their flags and statistics.
+    ///
+    /// Keys of the main database that don't name a database are skipped.
+    ///
+    /// Note: it will create a read transaction to traverse the main database.
+    pub fn list_dbs(&self) -> Result<Vec<DatabaseInfo>> {
+        list_dbs_in(&self.begin_ro_txn()?)
+    }
+}
+
+/// Lists the named databases visible to `txn`, see [`Environment::list_dbs()`].
+pub(crate) fn list_dbs_in<K: TransactionKind>(txn: &Transaction<K>) -> Result<Vec<DatabaseInfo>> {
+    let main = txn.open_db(None)?;
+    let cursor = txn.cursor(&main)?;
+
+    let mut dbs = Vec::new();
+    for result in cursor.iter_slices() {
+        let (name, _) = result?;
+        let Ok(name) = String::from_utf8(name.into_owned()) else {
+            continue;
+        };
+
+        let db = match txn.open_db(Some(&name)) {
+            Ok(db) => db,
+            Err(Error::Incompatible) => continue,
+            Err(err) => return Err(err),
+        };
+
+        dbs.push(DatabaseInfo {
+            flags: txn.db_flags(&db)?,
+            stat: txn.db_stat(&db)?,
+            name,
+        });
+    }
+
+    Ok(dbs)
+}
+
+/// Pushes a slot of the reader lock table to the `Vec<ReaderInfo>` behind `ctx`.
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn collect_reader(
+    ctx: *mut c_void,
+    _num: c_int,
+    slot: c_int,
+    pid: ffi::mdbx_pid_t,
+    thread: ffi::mdbx_tid_t,
+    txnid: u64,
+    lag: u64,
+    bytes_used: usize,
+    bytes_retained: usize,
+) -> c_int {
+    let readers = unsafe { &mut *(ctx as *mut Vec<ReaderInfo>) };
+    readers.push(ReaderInfo {
+        slot: slot as usize,
+        pid: pid as u32,
+        thread: thread as u64,
+        txnid,
+        lag,
+        bytes_used,
+        bytes_retained,
+    });
+
+    0
+}
+
+/// A slot of the reader lock table, see [`Environment::readers()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReaderInfo {
+    slot: usize,
+    pid: u32,
+    thread: u64,
+    txnid: u64,
+    lag: u64,
+    bytes_used: usize,
+    bytes_retained: usize,
+}
+
+impl ReaderInfo {
+    /// Index of the slot in the reader lock table.
+    #[inline]
+    pub const fn slot(&self) -> usize {
+        self.slot
+    }
+
+    /// Id of the process holding the slot.
+    #[inline]
+    pub const fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Id of the thread holding the slot.
+    #[inline]
+    pub const fn thread(&self) -> u64 {
+        self.thread
+    }
+
+    /// Id of the snapshot read by the slot, zero if it holds no read transaction.
+    #[inline]
+    pub const fn txnid(&self) -> u64 {
+        self.txnid
+    }
+
+    /// Number of transactions committed since the snapshot was taken.
+    #[inline]
+    pub const fn lag(&self) -> u64 {
+        self.lag
+    }
+
+    /// Number of bytes used by the snapshot.
+    #[inline]
+    pub const fn bytes_used(&self) -> usize {
+        self.bytes_used
+    }
+
+    /// Number of bytes retained by the snapshot, which would be reused once it is released.
+    #[inline]
+    pub const fn bytes_retained(&self) -> usize {
+        self.bytes_retained
+    }
+}
+
+/// A named database found by [`Environment::list_dbs()`].
+#[derive(Debug)]
+pub struct DatabaseInfo {
+    name: String,
+    flags: DatabaseFlags,
+    stat: Stat,
+}
+
+impl DatabaseInfo {
+    /// Name of the database.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Flags the database was created with.
+    pub const fn flags(&self) -> DatabaseFlags {
+        self.flags
+    }
+
+    /// Statistics of the database.
+    pub const fn stat(&self) -> &Stat {
+        &self.stat
+    }
 }
 
 /// Container type for Environment internals.
@@ -714,20 +942,6 @@ impl EnvironmentBuilder {
                     ))?;
                 }
 
-                #[cfg(unix)]
-                fn path_to_bytes<P: AsRef<Path>>(path: P) -> Vec<u8> {
-                    use std::os::unix::ffi::OsStrExt;
-                    path.as_ref().as_os_str().as_bytes().to_vec()
-                }
-
-                #[cfg(windows)]
-                fn path_to_bytes<P: AsRef<Path>>(path: P) -> Vec<u8> {
-                    // On Windows, could use std::os::windows::ffi::OsStrExt to encode_wide(),
-                    // but we end up with a Vec<u16> instead of a Vec<u8>, so that doesn't
-                    // really help.
-                    path.as_ref().to_string_lossy().to_string().into_bytes()
-                }
-
                 let path = match CString::new(path_to_bytes(path)) {
                     Ok(path) => path,
                     Err(_) => return Err(Error::Invalid),
@@ -946,6 +1160,20 @@ pub(crate) mod read_transactions {
     }
 }
 
+#[cfg(unix)]
+fn path_to_bytes<P: AsRef<Path>>(path: P) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_ref().as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(windows)]
+fn path_to_bytes<P: AsRef<Path>>(path: P) -> Vec<u8> {
+    // On Windows, could use std::os::windows::ffi::OsStrExt to encode_wide(),
+    // but we end up with a Vec<u16> instead of a Vec<u8>, so that doesn't
+    // really help.
+    path.as_ref().to_string_lossy().to_string().into_bytes()
+}
+
 /// Converts a [`HandleSlowReadersCallback`] to the actual FFI function pointer.
 #[allow(clippy::missing_transmute_annotations)]
 fn convert_hsr_fn(callback: Option<HandleSlowReadersCallback>) -> ffi::MDBX_hsr_func {
@@ -954,10 +1182,14 @@ fn convert_hsr_fn(callback: Option<HandleSlowReadersCallback>) -> ffi::MDBX_hsr_
 
 #[cfg(test)]
 mod tests {
-    use crate::{Environment, Error, Geometry, HandleSlowReadersReturnCode, PageSize, WriteFlags};
+    use crate::{
+        DatabaseFlags, Environment, Error, Geometry, HandleSlowReadersReturnCode, PageSize,
+        WriteFlags,
+    };
     use std::{
         ops::RangeInclusive,
         sync::atomic::{AtomicBool, Ordering},
+        time::{Duration, Instant},
     };
 
     #[test]
@@ -1032,4 +1264,76 @@ mod tests {
         // Expect the HSR to be called
         assert!(CALLED.load(Ordering::Relaxed));
     }
+
+    #[test]
+    fn test_list_dbs() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let env = Environment::builder()
+            .set_max_dbs(4)
+            .open(tempdir.path())
+            .unwrap();
+
+        {
+            let tx = env.begin_rw_txn().unwrap();
+            let plain = tx.create_db(Some("plain"), DatabaseFlags::empty()).unwrap();
+            tx.put(plain.dbi(), b"key", b"value", WriteFlags::empty())
+                .unwrap();
+            tx.create_db(Some("dup"), DatabaseFlags::DUP_SORT).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let dbs = env.list_dbs().unwrap();
+        let names = dbs.iter().map(|db| db.name()).collect::<Vec<_>>();
+        assert_eq!(names, ["dup", "plain"]);
+
+        assert!(dbs[0].flags().contains(DatabaseFlags::DUP_SORT));
+        assert_eq!(dbs[0].stat().entries(), 0);
+        assert_eq!(dbs[1].stat().entries(), 1);
+    }
+
+    #[test]
+    fn test_readers() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let env = Environment::builder().open(tempdir.path()).unwrap();
+
+        let _tx = env.begin_ro_txn().unwrap();
+
+        let readers = env.readers().unwrap();
+        assert!(readers
+            .iter()
+            .any(|reader| reader.pid() == std::process::id() && reader.txnid() > 0));
+        assert_eq!(env.reader_check().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_try_begin_rw_txn() {
+        let tempdir = tempfile::tempdir().unwrap();
+        let env = Environment::builder().open(tempdir.path()).unwrap();
+
+        let (locked, wait_locked) = std::sync::mpsc::channel();
+        let (release, wait_release) = std::sync::mpsc::channel::<()>();
+        std::thread::scope(|scope| {
+            let env = &env;
+            let writer = scope.spawn(move || {
+                let txn = env.begin_rw_txn().unwrap();
+                locked.send(()).unwrap();
+                wait_release.recv().unwrap();
+                txn.commit().unwrap();
+            });
+            wait_locked.recv().unwrap();
+
+            assert_eq!(env.try_begin_rw_txn().err(), Some(Error::Busy));
+            let timeout = Duration::from_millis(100);
+            let start = Instant::now();
+            assert_eq!(env.begin_rw_txn_timeout(timeout).err(), Some(Error::Busy));
+            assert!(start.elapsed() >= timeout);
+
+            release.send(()).unwrap();
+            let txn = env.begin_rw_txn_timeout(Duration::from_secs(10)).unwrap();
+            writer.join().unwrap();
+            txn.commit().unwrap();
+        });
+
+        env.try_begin_rw_txn().unwrap().commit().unwrap();
+    }
 }
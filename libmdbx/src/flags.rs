@@ -216,7 +216,7 @@ impl EnvironmentFlags {
 
 bitflags! {
     #[doc="Database options."]
-    #[derive(Default)]
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
     pub struct DatabaseFlags: MDBX_env_flags_t {
         const REVERSE_KEY = MDBX_REVERSEKEY;
         const DUP_SORT = MDBX_DUPSORT;
@@ -10,21 +10,24 @@
 pub extern crate ffi;
 
 pub use crate::{
+    check::{CheckError, CheckOptions, CheckReport, CheckValidator, TableReport},
     codec::*,
     cursor::{Cursor, Iter, IterDup},
     database::Database,
     environment::{
-        Environment, EnvironmentBuilder, EnvironmentKind, Geometry, HandleSlowReadersCallback,
-        HandleSlowReadersReturnCode, Info, PageSize, Stat,
+        DatabaseInfo, Environment, EnvironmentBuilder, EnvironmentKind, Geometry,
+        HandleSlowReadersCallback, HandleSlowReadersReturnCode, Info, PageOps, PageSize,
+        ReaderInfo, Stat,
     },
     error::{Error, Result},
     flags::*,
-    transaction::{CommitLatency, Transaction, TransactionKind, RO, RW},
+    transaction::{Canary, CommitLatency, ParkedTransaction, Transaction, TransactionKind, RO, RW},
 };
 
 #[cfg(feature = "read-tx-timeouts")]
 pub use crate::environment::read_transactions::MaxReadTransactionDuration;
 
+mod check;
 mod codec;
 mod cursor;
 mod database;
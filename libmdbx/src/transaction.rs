@@ -10,6 +10,7 @@ use ffi::{MDBX_txn_flags_t, MDBX_TXN_RDONLY, MDBX_TXN_READWRITE};
 use indexmap::IndexSet;
 use parking_lot::{Mutex, MutexGuard};
 use std::{
+    cmp::Ordering,
     ffi::{c_uint, c_void},
     fmt::{self, Debug},
     mem::size_of,
@@ -247,12 +248,17 @@ where
 
     /// Gets the option flags for the given database in the transaction.
     pub fn db_flags(&self, db: &Database) -> Result<DatabaseFlags> {
+        self.db_flags_with_dbi(db.dbi())
+    }
+
+    /// Gets the option flags of the database with the given dbi.
+    pub fn db_flags_with_dbi(&self, dbi: ffi::MDBX_dbi) -> Result<DatabaseFlags> {
         let mut flags: c_uint = 0;
         unsafe {
             self.txn_execute(|txn| {
                 mdbx_result(ffi::mdbx_dbi_flags_ex(
                     txn,
-                    db.dbi(),
+                    dbi,
                     &mut flags,
                     ptr::null_mut(),
                 ))
@@ -264,6 +270,22 @@ where
         Ok(DatabaseFlags::from_bits_truncate(flags.try_into().unwrap()))
     }
 
+    /// Compares two keys of the database with the given dbi in the order MDBX stores them, which
+    /// follows the flags of the database, see `mdbx_cmp`.
+    pub fn compare_keys(&self, dbi: ffi::MDBX_dbi, a: &[u8], b: &[u8]) -> Result<Ordering> {
+        let a = ffi::MDBX_val {
+            iov_len: a.len(),
+            iov_base: a.as_ptr() as *mut c_void,
+        };
+        let b = ffi::MDBX_val {
+            iov_len: b.len(),
+            iov_base: b.as_ptr() as *mut c_void,
+        };
+
+        self.txn_execute(|txn| unsafe { ffi::mdbx_cmp(txn, dbi, &a, &b) })
+            .map(|ordering| ordering.cmp(&0))
+    }
+
     /// Retrieves database statistics.
     pub fn db_stat(&self, db: &Database) -> Result<Stat> {
         self.db_stat_with_dbi(db.dbi())
@@ -285,6 +307,76 @@ where
         }
     }
 
+    /// Estimates the number of items from `begin` up to `end` with `mdbx_estimate_range`, [None]
+    /// standing for the first and the last item of the database.
+    ///
+    /// The estimate is exact for small databases and degrades gracefully with their size, it's
+    /// negative if `end` is before `begin`.
+    pub fn estimate_range(
+        &self,
+        dbi: ffi::MDBX_dbi,
+        begin: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> Result<isize> {
+        let to_val = |key: &[u8]| ffi::MDBX_val {
+            iov_len: key.len(),
+            iov_base: key.as_ptr() as *mut c_void,
+        };
+        let begin = begin.map(to_val);
+        let end = end.map(to_val);
+        let mut distance: isize = 0;
+
+        self.txn_execute(|txn| unsafe {
+            mdbx_result(ffi::mdbx_estimate_range(
+                txn,
+                dbi,
+                begin.as_ref().map_or(ptr::null(), |key| key as *const _),
+                ptr::null(),
+                end.as_ref().map_or(ptr::null(), |key| key as *const _),
+                ptr::null(),
+                &mut distance,
+            ))
+        })??;
+
+        Ok(distance)
+    }
+
+    /// Reads the canary of the environment with `mdbx_canary_get`, as of this transaction.
+    pub fn canary(&self) -> Result<Canary> {
+        let mut canary = ffi::MDBX_canary {
+            x: 0,
+            y: 0,
+            z: 0,
+            v: 0,
+        };
+        self.txn_execute(|txn| unsafe { mdbx_result(ffi::mdbx_canary_get(txn, &mut canary)) })??;
+
+        Ok(Canary {
+            x: canary.x,
+            y: canary.y,
+            z: canary.z,
+            v: canary.v,
+        })
+    }
+
+    /// Returns the current value of the sequence of the database, see
+    /// [`Transaction::next_sequence`].
+    pub fn current_sequence(&self, dbi: ffi::MDBX_dbi) -> Result<u64> {
+        // The sequence can't overflow when not increased.
+        self.sequence(dbi, 0).map(Option::unwrap_or_default)
+    }
+
+    /// Reads the sequence of the database with `mdbx_dbi_sequence` and increases it by
+    /// `increment`, returning its value before the increase or [None] if it would overflow.
+    fn sequence(&self, dbi: ffi::MDBX_dbi, increment: u64) -> Result<Option<u64>> {
+        let mut value = 0;
+        let overflow = self.txn_execute(|txn| unsafe {
+            mdbx_result(ffi::mdbx_dbi_sequence(txn, dbi, &mut value, increment))
+        })??;
+
+        Ok((!overflow).then_some(value))
+    }
+
     /// Open a new cursor on the given database.
     pub fn cursor(&self, db: &Database) -> Result<Cursor<K>> {
         Cursor::new(self.clone(), db.dbi())
@@ -426,6 +518,29 @@ impl Transaction<RW> {
         self.open_db_with_flags(name, flags | DatabaseFlags::CREATE)
     }
 
+    /// Puts the `x`, `y` and `z` fields of the canary with `mdbx_canary_put`, the `v` field being
+    /// set to the id of this transaction. The canary is stored once the transaction commits.
+    pub fn put_canary(&self, canary: &Canary) -> Result<()> {
+        let canary = ffi::MDBX_canary {
+            x: canary.x,
+            y: canary.y,
+            z: canary.z,
+            v: 0,
+        };
+        self.txn_execute(|txn| unsafe { mdbx_result(ffi::mdbx_canary_put(txn, &canary)) })??;
+
+        Ok(())
+    }
+
+    /// Increases the sequence of the database by `increment`, returning its value before the
+    /// increase or [None] if it would overflow.
+    ///
+    /// The sequence is a 64-bit counter stored along with the database, starting at zero and
+    /// updated with the transaction.
+    pub fn next_sequence(&self, dbi: ffi::MDBX_dbi, increment: u64) -> Result<Option<u64>> {
+        self.sequence(dbi, increment)
+    }
+
     /// Stores an item into a database.
     ///
     /// This function stores key/data pairs in the database. The default
@@ -553,6 +668,47 @@ impl Transaction<RW> {
 }
 
 impl Transaction<RO> {
+    /// Parks the transaction with `mdbx_txn_reset`, releasing its snapshot while keeping the
+    /// handle, so that [`ParkedTransaction::renew`] can reuse it instead of beginning a new one.
+    ///
+    /// Fails with [`Error::Busy`] if the transaction is still shared with a clone or a cursor, the
+    /// transaction is aborted as usual in that case.
+    pub fn reset(mut self) -> Result<ParkedTransaction> {
+        let Some(inner) = Arc::get_mut(&mut self.inner) else {
+            return Err(Error::Busy);
+        };
+        inner.primed_dbis.get_mut().clear();
+
+        // Stop tracking the transaction before taking its lock, the monitor of the timeouts takes
+        // them in the opposite order. A parked transaction keeps its pointer, so MDBX can't hand
+        // it out to another transaction until the parked one is dropped.
+        #[cfg(feature = "read-tx-timeouts")]
+        self.env()
+            .txn_manager()
+            .remove_active_read_transaction(self.inner.txn.txn);
+        self.inner.txn.reset()?;
+
+        Ok(ParkedTransaction { inner: self.inner })
+    }
+
+    /// Renews the transaction on the latest snapshot if the read transaction timeout ended it,
+    /// tracking it again from now on. Returns `true` if the transaction was renewed.
+    ///
+    /// The cursors of the transaction have to be renewed with [`Cursor::renew`] before being used
+    /// again.
+    #[cfg(feature = "read-tx-timeouts")]
+    pub fn renew_timed_out(&self) -> Result<bool> {
+        if !self.inner.txn.renew_if_timed_out()? {
+            return Ok(false);
+        }
+
+        let txn_manager = self.env().txn_manager();
+        txn_manager.remove_active_read_transaction(self.inner.txn.txn);
+        txn_manager.add_active_read_transaction(self.inner.txn.txn, self.inner.txn.clone());
+
+        Ok(true)
+    }
+
     /// Closes the database handle.
     ///
     /// # Safety
@@ -588,6 +744,37 @@ impl Transaction<RW> {
     }
 }
 
+/// A read-only transaction parked by [`Transaction::reset`].
+///
+/// It holds no snapshot and isn't subject to the read transaction timeouts, but keeps its reader
+/// slot until renewed or dropped.
+pub struct ParkedTransaction {
+    inner: Arc<TransactionInner<RO>>,
+}
+
+impl ParkedTransaction {
+    /// Renews the transaction with `mdbx_txn_renew`, on the latest snapshot of the environment.
+    ///
+    /// The transaction is aborted if the renewal fails.
+    pub fn renew(self) -> Result<Transaction<RO>> {
+        self.inner.txn.renew()?;
+
+        #[cfg(feature = "read-tx-timeouts")]
+        self.inner
+            .env
+            .txn_manager()
+            .add_active_read_transaction(self.inner.txn.txn, self.inner.txn.clone());
+
+        Ok(Transaction { inner: self.inner })
+    }
+}
+
+impl fmt::Debug for ParkedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParkedTransaction").finish_non_exhaustive()
+    }
+}
+
 /// A shareable pointer to an MDBX transaction.
 #[derive(Debug, Clone)]
 pub(crate) struct TransactionPtr {
@@ -626,6 +813,43 @@ impl TransactionPtr {
             .store(true, std::sync::atomic::Ordering::SeqCst);
     }
 
+    /// Resets the read transaction with `mdbx_txn_reset`, unless the `TxnManager` already did so
+    /// when timing it out.
+    fn reset(&self) -> Result<()> {
+        let _lck = self.lock();
+
+        #[cfg(feature = "read-tx-timeouts")]
+        if self.is_timed_out() {
+            self.timed_out
+                .store(false, std::sync::atomic::Ordering::SeqCst);
+            return Ok(());
+        }
+
+        mdbx_result(unsafe { ffi::mdbx_txn_reset(self.txn) })?;
+        Ok(())
+    }
+
+    /// Renews the read transaction reset by [`TransactionPtr::reset`].
+    fn renew(&self) -> Result<()> {
+        let _lck = self.lock();
+        mdbx_result(unsafe { ffi::mdbx_txn_renew(self.txn) })?;
+        Ok(())
+    }
+
+    /// Renews the transaction if the `TxnManager` timed it out, returning `true` if it did.
+    #[cfg(feature = "read-tx-timeouts")]
+    fn renew_if_timed_out(&self) -> Result<bool> {
+        let _lck = self.lock();
+        if !self.is_timed_out() {
+            return Ok(false);
+        }
+
+        mdbx_result(unsafe { mdbx_txn_renew(self.txn) })?;
+        self.timed_out
+            .store(false, std::sync::atomic::Ordering::SeqCst);
+        Ok(true)
+    }
+
     /// Acquires the inner transaction lock to guarantee exclusive access to the transaction
     /// pointer.
     fn lock(&self) -> MutexGuard<'_, ()> {
@@ -685,6 +909,17 @@ impl TransactionPtr {
     }
 }
 
+/// Markers stored in the meta page of the environment, written atomically with the commit of the
+/// transaction that put them, see [`Transaction::put_canary`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Canary {
+    pub x: u64,
+    pub y: u64,
+    pub z: u64,
+    /// Id of the transaction that put the canary, set by MDBX.
+    pub v: u64,
+}
+
 /// Commit latencies info.
 ///
 /// Contains information about latency of commit stages.
@@ -779,5 +1014,6 @@ mod tests {
     const fn test_txn_send_sync() {
         assert_send_sync::<Transaction<RO>>();
         assert_send_sync::<Transaction<RW>>();
+        assert_send_sync::<ParkedTransaction>();
     }
 }
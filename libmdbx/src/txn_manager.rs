@@ -425,6 +425,38 @@ mod read_transactions {
             }
         }
 
+        #[test]
+        fn txn_manager_read_transactions_reset_renew() {
+            let dir = tempdir().unwrap();
+            let env = Environment::builder()
+                .set_max_read_transaction_duration(MaxReadTransactionDuration::Set(
+                    Duration::from_secs(1),
+                ))
+                .open(dir.path())
+                .unwrap();
+
+            let read_transactions = env.txn_manager().read_transactions.as_ref().unwrap();
+
+            // A shared transaction can't be parked.
+            let tx = env.begin_ro_txn().unwrap();
+            let _clone = tx.clone();
+            assert_eq!(tx.reset().err(), Some(Error::Busy));
+
+            // A parked transaction isn't tracked, and is tracked again with the same pointer once
+            // renewed.
+            let tx = env.begin_ro_txn().unwrap();
+            let tx_ptr = tx.txn() as usize;
+            let parked = tx.reset().unwrap();
+            assert!(!read_transactions.active.contains_key(&tx_ptr));
+
+            let tx = parked.renew().unwrap();
+            assert_eq!(tx.txn() as usize, tx_ptr);
+            assert!(read_transactions.active.contains_key(&tx_ptr));
+            tx.open_db(None).unwrap();
+            tx.commit().unwrap();
+            assert!(!read_transactions.active.contains_key(&tx_ptr));
+        }
+
         #[test]
         fn txn_manager_read_transactions_duration_unbounded() {
             let dir = tempdir().unwrap();
@@ -4,21 +4,21 @@ macro_rules! table_value_codecs_with_zc {
     ($table_value:ident) => {
         impl alloy_rlp::Encodable for $table_value {
             fn encode(&self, out: &mut dyn alloy_primitives::bytes::BufMut) {
-                let encoded = rkyv::to_bytes::<_, 256>(self).unwrap();
-
-                out.put_slice(&encoded)
+                // `Encodable` can't fail, a failure is reported as a write error by the transaction.
+                match rkyv::to_bytes::<_, 256>(self) {
+                    Ok(encoded) => out.put_slice(&encoded),
+                    Err(e) => $crate::report_codec_error("serialize", e),
+                }
             }
         }
 
         impl alloy_rlp::Decodable for $table_value {
             fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
-                let archived: &paste::paste!([<Archived $table_value>]) =
-                unsafe { rkyv::archived_root::<Self>(&buf[..]) };
-
-
-                let this = rkyv::Deserialize::deserialize(archived, &mut rkyv::Infallible).unwrap();
+                if buf.len() < std::mem::size_of::<paste::paste!([<Archived $table_value>])>() {
+                    return Err(alloy_rlp::Error::InputTooShort);
+                }
 
-                Ok(this)
+                $crate::deserialize_archived::<Self>(buf)
             }
         }
 
@@ -28,17 +28,17 @@ macro_rules! table_value_codecs_with_zc {
             fn compress_to_buf<B: alloy_primitives::bytes::BufMut + AsMut<[u8]>>(&self, buf: &mut B) {
                 let mut encoded = Vec::new();
                 alloy_rlp::Encodable::encode(&self, &mut encoded);
-                let encoded_compressed = zstd::encode_all(&*encoded, 0).unwrap();
-
-                buf.put_slice(&encoded_compressed);
+                match zstd::encode_all(&*encoded, 0) {
+                    Ok(encoded_compressed) => buf.put_slice(&encoded_compressed),
+                    Err(e) => $crate::report_codec_error("compress", e),
+                }
             }
         }
 
         impl reth_db_api::table::Decompress for $table_value {
             fn decompress(value: &[u8]) -> Result<Self, reth_storage_errors::db::DatabaseError> {
-                let binding = value.to_vec();
-
-                let encoded_decompressed = zstd::decode_all(&*binding).unwrap();
+                let encoded_decompressed = zstd::decode_all(value)
+                    .map_err(|_| reth_storage_errors::db::DatabaseError::Decode)?;
                 let buf = &mut encoded_decompressed.as_slice();
 
                 alloy_rlp::Decodable::decode(buf).map_err(|_| reth_storage_errors::db::DatabaseError::Decode)
@@ -112,21 +112,21 @@ macro_rules! table_value_codecs_with_zc {
     ($table_value:ident) => {
         impl libmdbx_bindings::Encodable for $table_value {
             fn encode(&self, out: &mut dyn libmdbx_bindings::BufMut) {
-                let encoded = libmdbx_bindings::to_bytes::<_, 256>(self).unwrap();
-
-                out.put_slice(&encoded)
+                // `Encodable` can't fail, a failure is reported as a write error by the transaction.
+                match libmdbx_bindings::to_bytes::<_, 256>(self) {
+                    Ok(encoded) => out.put_slice(&encoded),
+                    Err(e) => libmdbx_bindings::report_codec_error("serialize", e),
+                }
             }
         }
 
         impl libmdbx_bindings::Decodable for $table_value {
             fn decode(buf: &mut &[u8]) -> libmdbx_bindings::RlpResult<Self> {
-                let archived: &$crate::paste!([<Archived $table_value>]) =
-                unsafe { libmdbx_bindings::archived_root::<Self>(&buf[..]) };
-
-
-                let this = libmdbx_bindings::re_export_rkyv::Deserialize::deserialize(archived, &mut libmdbx_bindings::Infallible).unwrap();
+                if buf.len() < std::mem::size_of::<$crate::paste!([<Archived $table_value>])>() {
+                    return Err(libmdbx_bindings::RlpError::InputTooShort);
+                }
 
-                Ok(this)
+                libmdbx_bindings::deserialize_archived::<Self>(buf)
             }
         }
 
@@ -136,17 +136,17 @@ macro_rules! table_value_codecs_with_zc {
             fn compress_to_buf<B: libmdbx_bindings::AlloyBytesMut + AsMut<[u8]>>(&self, buf: &mut B) {
                 let mut encoded = Vec::new();
                 libmdbx_bindings::Encodable::encode(&self, &mut encoded);
-                let encoded_compressed = libmdbx_bindings::encode_all(&*encoded, 0).unwrap();
-
-                buf.put_slice(&encoded_compressed);
+                match libmdbx_bindings::encode_all(&*encoded, 0) {
+                    Ok(encoded_compressed) => buf.put_slice(&encoded_compressed),
+                    Err(e) => libmdbx_bindings::report_codec_error("compress", e),
+                }
             }
         }
 
         impl libmdbx_bindings::Decompress for $table_value {
             fn decompress(value: &[u8]) -> Result<Self, libmdbx_bindings::DatabaseError> {
-                let binding = value.to_vec();
-
-                let encoded_decompressed = libmdbx_bindings::decode_all(&*binding).unwrap();
+                let encoded_decompressed = libmdbx_bindings::decode_all(value)
+                    .map_err(|_| libmdbx_bindings::DatabaseError::Decode)?;
                 let buf = &mut encoded_decompressed.as_slice();
 
                 libmdbx_bindings::Decodable::decode(buf).map_err(|_| libmdbx_bindings::DatabaseError::Decode)
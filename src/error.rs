@@ -0,0 +1,264 @@
+//! Errors of the table operations, carrying the table and key they happened on.
+
+use std::fmt::{self, Write};
+
+use reth_db::{DatabaseError, DatabaseWriteOperation};
+use reth_storage_errors::db::{DatabaseErrorInfo, DatabaseWriteError};
+
+/// Number of key bytes shown in the message of an [`Error`].
+const KEY_PREVIEW_LEN: usize = 32;
+
+/// Operation during which an [`Error`] happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// Opening the environment or a table.
+    Open,
+    /// Creating a table.
+    CreateTable,
+    /// Beginning a transaction.
+    BeginTx,
+    /// Committing a transaction.
+    Commit,
+    /// Reading a row.
+    Get,
+    /// Writing a row.
+    Put,
+    /// Deleting a row or clearing a table.
+    Delete,
+    /// Opening a cursor.
+    Cursor,
+    /// Reading the statistics of a table.
+    Stats,
+    /// Setting an option of the environment.
+    SetOption,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Open => "open",
+            Self::CreateTable => "create table",
+            Self::BeginTx => "begin transaction",
+            Self::Commit => "commit",
+            Self::Get => "get",
+            Self::Put => "put",
+            Self::Delete => "delete",
+            Self::Cursor => "cursor",
+            Self::Stats => "stats",
+            Self::SetOption => "set option",
+        })
+    }
+}
+
+/// Where an [`Error`] happened.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorContext {
+    operation: Operation,
+    table: Option<&'static str>,
+    key: Option<Vec<u8>>,
+}
+
+impl ErrorContext {
+    /// Context of an operation that doesn't target a table.
+    pub const fn new(operation: Operation) -> Self {
+        Self {
+            operation,
+            table: None,
+            key: None,
+        }
+    }
+
+    /// Sets the table the operation targets.
+    pub const fn with_table(mut self, table: &'static str) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// Sets the encoded key the operation targets.
+    pub fn with_key(mut self, key: &[u8]) -> Self {
+        self.key = Some(key.to_vec());
+        self
+    }
+
+    /// Operation that failed.
+    pub const fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    /// Table the operation targeted, if any.
+    pub const fn table(&self) -> Option<&'static str> {
+        self.table
+    }
+
+    /// Encoded key the operation targeted, if any.
+    pub fn key(&self) -> Option<&[u8]> {
+        self.key.as_deref()
+    }
+
+    /// Hex preview of the key, truncated after [`KEY_PREVIEW_LEN`] bytes.
+    pub fn key_preview(&self) -> Option<String> {
+        let key = self.key.as_deref()?;
+        let mut out = String::from("0x");
+        for byte in key.iter().take(KEY_PREVIEW_LEN) {
+            let _ = write!(out, "{byte:02x}");
+        }
+        if key.len() > KEY_PREVIEW_LEN {
+            let _ = write!(out, "... ({} bytes)", key.len());
+        }
+
+        Some(out)
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some(table) = self.table {
+            write!(f, " on table {table}")?;
+        }
+        if let Some(key) = self.key_preview() {
+            write!(f, " with key {key}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Error of the crate, with the context of the operation that failed.
+///
+/// Converts into [`DatabaseError`] for the reth traits: MDBX errors keep their variant and code
+/// with the context appended to the message, and codec errors become [`DatabaseError::Other`]
+/// since [`DatabaseError::Decode`] can't carry the table and key.
+#[derive(Debug)]
+pub enum Error {
+    /// MDBX returned an error.
+    Mdbx(libmdbx_native::Error, ErrorContext),
+    /// A key or a value couldn't be encoded or decoded.
+    Codec(Box<dyn std::error::Error + Send + Sync>, ErrorContext),
+}
+
+impl Error {
+    /// Where the error happened.
+    pub const fn context(&self) -> &ErrorContext {
+        match self {
+            Self::Mdbx(_, context) | Self::Codec(_, context) => context,
+        }
+    }
+
+    /// Maps the error code returned by an MDBX function, `Ok(true)` standing for
+    /// `MDBX_RESULT_TRUE`.
+    pub(crate) fn check(code: std::ffi::c_int, context: ErrorContext) -> Result<bool, Self> {
+        match code {
+            reth_mdbx_sys::MDBX_SUCCESS => Ok(false),
+            reth_mdbx_sys::MDBX_RESULT_TRUE => Ok(true),
+            _ => Err(Self::Mdbx(
+                libmdbx_native::Error::from_err_code(code),
+                context,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mdbx(error, context) => write!(f, "{context} failed: {error}"),
+            Self::Codec(error, context) => write!(f, "{context} failed: invalid encoding: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Mdbx(error, _) => Some(error),
+            Self::Codec(error, _) => Some(error.as_ref()),
+        }
+    }
+}
+
+impl From<Error> for DatabaseError {
+    fn from(error: Error) -> Self {
+        let (inner, context) = match &error {
+            Error::Mdbx(inner, context) => (inner, context),
+            Error::Codec(..) => return Self::Other(error.to_string()),
+        };
+
+        let info = DatabaseErrorInfo {
+            message: error.to_string().into(),
+            code: inner.to_err_code(),
+        };
+
+        match (context.operation, context.table) {
+            (Operation::Put, Some(table_name)) => DatabaseWriteError {
+                info,
+                operation: DatabaseWriteOperation::Put,
+                table_name,
+                key: context.key.clone().unwrap_or_default(),
+            }
+            .into(),
+            (Operation::Open | Operation::SetOption, _) => Self::Open(info),
+            (Operation::CreateTable, _) => Self::CreateTable(info),
+            (Operation::BeginTx, _) => Self::InitTx(info),
+            (Operation::Commit, _) => Self::Commit(info),
+            (Operation::Delete, _) => Self::Delete(info),
+            (Operation::Cursor, _) => Self::InitCursor(info),
+            (Operation::Stats, _) => Self::Stats(info),
+            (Operation::Get, _) => Self::Read(info),
+            (Operation::Put, None) => Self::Other(error.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libmdbx_native::WriteFlags;
+    use reth_db::{
+        cursor::DbCursorRO,
+        table::Encode,
+        transaction::{DbTx, DbTxMut},
+    };
+
+    use super::*;
+    use crate::{IntegerKey, test_utils};
+
+    db_table!((Plain) | u64, IntegerKey<u64>);
+
+    tables!(ErrorTables, 1, [Plain]);
+
+    #[test]
+    fn decode_errors_name_the_table_and_key() {
+        let (_dir, db) = test_utils::provider::<ErrorTables>();
+        db.write(|tx| {
+            // A value of the wrong width can't be decoded as an `IntegerKey<u64>`.
+            let dbi = tx.inner.open_db(Some("Plain")).unwrap().dbi();
+            tx.inner
+                .put(dbi, 7u64.encode(), [1u8, 2, 3], WriteFlags::UPSERT)
+                .unwrap();
+        })
+        .unwrap();
+
+        let (get, walk) = db
+            .read(|tx| {
+                let get = tx.get::<Plain>(7).unwrap_err();
+                let walk = tx
+                    .cursor_read::<Plain>()
+                    .unwrap()
+                    .walk(None)
+                    .unwrap()
+                    .next()
+                    .unwrap()
+                    .unwrap_err();
+                (get, walk)
+            })
+            .unwrap();
+
+        for err in [get, walk] {
+            let DatabaseError::Other(message) = &err else {
+                panic!("unexpected error {err:?}");
+            };
+            assert!(message.contains("on table Plain"), "{message}");
+            assert!(message.contains("with key 0x0000000000000007"), "{message}");
+        }
+    }
+}
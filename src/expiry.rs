@@ -0,0 +1,381 @@
+use std::{
+    borrow::Cow,
+    sync::{
+        Arc,
+        mpsc::{RecvTimeoutError, Sender, channel},
+    },
+    thread::JoinHandle,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use libmdbx_native::{RW, Transaction, WriteFlags};
+use reth_db::{DatabaseError, DatabaseWriteOperation, transaction::DbTx};
+use reth_storage_errors::db::DatabaseWriteError;
+use tracing::warn;
+
+use crate::implementation::{DatabaseEnv, LibmdbxTx};
+
+/// Name of the DUPSORT table mapping expiry deadlines to the rows that expire at them.
+pub(crate) const EXPIRY_INDEX: &str = "__ExpiryIndex";
+
+/// Length of the deadline stored in front of every value of an expiring table.
+const DEADLINE_LEN: usize = size_of::<u64>();
+
+/// Returns the current time in milliseconds since the unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Returns the deadline of a row written now with the given time-to-live.
+pub(crate) fn deadline(ttl: Duration) -> u64 {
+    now().saturating_add(ttl.as_millis() as u64)
+}
+
+/// Returns `true` if a row with the given deadline is expired.
+pub(crate) fn is_expired(deadline: u64) -> bool {
+    deadline <= now()
+}
+
+/// Prefixes a compressed value with its deadline.
+pub(crate) fn stamp(deadline: u64, value: &[u8]) -> Vec<u8> {
+    let mut stamped = Vec::with_capacity(DEADLINE_LEN + value.len());
+    stamped.extend_from_slice(&deadline.to_be_bytes());
+    stamped.extend_from_slice(value);
+    stamped
+}
+
+/// Splits a stored value into its deadline and the compressed value.
+pub(crate) fn split(stored: &[u8]) -> Result<(u64, &[u8]), DatabaseError> {
+    if stored.len() < DEADLINE_LEN {
+        return Err(DatabaseError::Decode);
+    }
+
+    let (deadline, value) = stored.split_at(DEADLINE_LEN);
+    let mut buf = [0u8; DEADLINE_LEN];
+    buf.copy_from_slice(deadline);
+
+    Ok((u64::from_be_bytes(buf), value))
+}
+
+/// Like [`split`], but keeps the value borrowed from the database when possible.
+pub(crate) fn strip(stored: Cow<'_, [u8]>) -> Result<(u64, Cow<'_, [u8]>), DatabaseError> {
+    let (deadline, _) = split(&stored)?;
+    let value = match stored {
+        Cow::Borrowed(v) => Cow::Borrowed(&v[DEADLINE_LEN..]),
+        Cow::Owned(mut v) => {
+            v.drain(..DEADLINE_LEN);
+            Cow::Owned(v)
+        }
+    };
+
+    Ok((deadline, value))
+}
+
+/// Length of the table name length stored in front of every expiry index entry.
+const TABLE_LEN_LEN: usize = size_of::<u16>();
+
+/// Records that the row `key` of `table` expires at `deadline`.
+///
+/// Entries are never updated in place: the reaper skips entries whose deadline no longer matches
+/// the one stored with the row.
+pub(crate) fn track(
+    txn: &Transaction<RW>,
+    table: &str,
+    deadline: u64,
+    key: &[u8],
+) -> Result<(), DatabaseError> {
+    let entry = entry(table, key)?;
+
+    let db = txn
+        .open_db(Some(EXPIRY_INDEX))
+        .map_err(|e| DatabaseError::Open(e.into()))?;
+    txn.put(db.dbi(), deadline.to_be_bytes(), entry, WriteFlags::UPSERT)
+        .map_err(|e| {
+            DatabaseWriteError {
+                info: e.into(),
+                operation: DatabaseWriteOperation::Put,
+                table_name: EXPIRY_INDEX,
+                key: deadline.to_be_bytes().to_vec(),
+            }
+            .into()
+        })
+}
+
+/// Builds the expiry index entry of the row `key` of `table`.
+fn entry(table: &str, key: &[u8]) -> Result<Vec<u8>, DatabaseError> {
+    let len = u16::try_from(table.len()).map_err(|_| {
+        DatabaseError::Other(format!(
+            "name of the expiring table {table} is longer than {} bytes",
+            u16::MAX
+        ))
+    })?;
+
+    let mut entry = Vec::with_capacity(TABLE_LEN_LEN + table.len() + key.len());
+    entry.extend_from_slice(&len.to_be_bytes());
+    entry.extend_from_slice(table.as_bytes());
+    entry.extend_from_slice(key);
+
+    Ok(entry)
+}
+
+/// Splits an expiry index entry into the table name and the encoded key of the row.
+pub(crate) fn parse_entry(entry: &[u8]) -> Result<(&str, &[u8]), DatabaseError> {
+    if entry.len() < TABLE_LEN_LEN {
+        return Err(DatabaseError::Decode);
+    }
+
+    let (len, rest) = entry.split_at(TABLE_LEN_LEN);
+    let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+    if rest.len() < len {
+        return Err(DatabaseError::Decode);
+    }
+
+    let (table, key) = rest.split_at(len);
+    let table = std::str::from_utf8(table).map_err(|_| DatabaseError::Decode)?;
+
+    Ok((table, key))
+}
+
+/// Configuration of the background thread deleting expired rows.
+#[derive(Clone, Copy, Debug)]
+pub struct ReaperConfig {
+    /// Time between two reaping rounds.
+    interval: Duration,
+    /// Maximum number of expiry entries handled by a single write transaction.
+    budget: usize,
+}
+
+impl Default for ReaperConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            budget: 10_000,
+        }
+    }
+}
+
+impl ReaperConfig {
+    /// Sets the time between two reaping rounds.
+    pub const fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the maximum number of expiry entries handled by a single write transaction.
+    ///
+    /// A round keeps opening new transactions until it runs out of expired entries.
+    pub const fn with_budget(mut self, budget: usize) -> Self {
+        self.budget = if budget == 0 { 1 } else { budget };
+        self
+    }
+}
+
+/// Handle to the reaper thread. The thread is stopped when the handle is dropped.
+#[derive(Debug)]
+pub struct ReaperHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ReaperHandle {
+    pub(crate) fn spawn(env: Arc<DatabaseEnv>, config: ReaperConfig) -> std::io::Result<Self> {
+        let (stop, stopped) = channel::<()>();
+
+        let thread = std::thread::Builder::new()
+            .name("libmdbx-bindings-reaper".to_string())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(config.interval) {
+                    if let Err(err) = reap(&env, config.budget) {
+                        warn!(target: "libmdbx_bindings", %err, "Failed to delete expired rows");
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+
+    /// Stops the reaper, waiting for the running round to finish.
+    pub fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for ReaperHandle {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Runs a reaping round, one write transaction per `budget` expiry entries.
+fn reap(env: &DatabaseEnv, budget: usize) -> Result<(), DatabaseError> {
+    loop {
+        let tx = LibmdbxTx::new_rw_tx(env)?;
+        let processed = tx.reap_expired(budget)?;
+        tx.commit()?;
+
+        if processed < budget {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use reth_db::{
+        cursor::DbCursorRO,
+        table::Encode,
+        transaction::{DbTx, DbTxMut},
+    };
+
+    use super::*;
+    use crate::{IntegerKey, test_utils};
+
+    db_table!((Expired, ttl = Duration::ZERO) | u64, IntegerKey<u64>);
+    db_table!(
+        (Live, ttl = Duration::from_secs(3600)) | u64,
+        IntegerKey<u64>
+    );
+
+    tables!(ExpiringTables, 2, [Expired, Live]);
+
+    /// Returns the number of rows of the expiry index.
+    fn tracked(tx: &LibmdbxTx<impl libmdbx_native::TransactionKind>) -> usize {
+        let db = tx.inner.open_db(Some(EXPIRY_INDEX)).unwrap();
+        tx.inner.db_stat(&db).unwrap().entries()
+    }
+
+    #[test]
+    fn entry_round_trip() {
+        let short = entry("Table", &[1, 2, 3]).unwrap();
+        assert_eq!(parse_entry(&short).unwrap(), ("Table", &[1, 2, 3][..]));
+
+        // Longer than what a `u8` length could hold.
+        let name = "T".repeat(300);
+        let long = entry(&name, &[4]).unwrap();
+        assert_eq!(parse_entry(&long).unwrap(), (name.as_str(), &[4][..]));
+
+        assert!(entry(&"T".repeat(u16::MAX as usize + 1), &[]).is_err());
+        assert!(parse_entry(&[0]).is_err());
+        assert!(parse_entry(&[0, 4, b'T']).is_err());
+    }
+
+    #[test]
+    fn expired_rows_are_hidden() {
+        let (_dir, db) = test_utils::provider::<ExpiringTables>();
+        db.write(|tx| {
+            tx.put::<Expired>(1, IntegerKey(1))?;
+            tx.put::<Live>(1, IntegerKey(1))
+        })
+        .unwrap()
+        .unwrap();
+
+        db.read(|tx| {
+            assert_eq!(tx.get::<Expired>(1).unwrap(), None);
+            assert_eq!(tx.get::<Live>(1).unwrap(), Some(IntegerKey(1)));
+
+            let mut cursor = tx.cursor_read::<Expired>().unwrap();
+            assert_eq!(cursor.walk(None).unwrap().count(), 0);
+            let mut cursor = tx.cursor_read::<Live>().unwrap();
+            assert_eq!(cursor.walk(None).unwrap().count(), 1);
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn reaper_respects_budget() {
+        let (_dir, db) = test_utils::provider::<ExpiringTables>();
+        db.write(|tx| {
+            for key in 0..5 {
+                tx.put::<Expired>(key, IntegerKey(key))?;
+            }
+            tx.put::<Live>(0, IntegerKey(0))
+        })
+        .unwrap()
+        .unwrap();
+
+        // A single transaction only handles `budget` entries.
+        assert_eq!(db.write(|tx| tx.reap_expired(2)).unwrap().unwrap(), 2);
+        db.read(|tx| {
+            assert_eq!(tx.entries::<Expired>().unwrap(), 3);
+            assert_eq!(tracked(tx), 4);
+        })
+        .unwrap();
+
+        let reaper = db
+            .start_reaper(
+                ReaperConfig::default()
+                    .with_interval(Duration::from_millis(10))
+                    .with_budget(2),
+            )
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while db.read(|tx| tx.entries::<Expired>()).unwrap().unwrap() > 0 {
+            assert!(Instant::now() < deadline, "expired rows were not reaped");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        reaper.stop();
+
+        db.read(|tx| {
+            assert_eq!(tx.entries::<Live>().unwrap(), 1);
+            assert_eq!(tracked(tx), 1);
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn reaper_drops_corrupt_entries() {
+        let (_dir, db) = test_utils::provider::<ExpiringTables>();
+        db.write(|tx| {
+            tx.put::<Expired>(1, IntegerKey(1))?;
+            tx.put::<Live>(1, IntegerKey(1))?;
+
+            let index = tx.inner.open_db(Some(EXPIRY_INDEX)).unwrap();
+            // A deadline and a table name length that don't fit.
+            tx.inner
+                .put(
+                    index.dbi(),
+                    [0u8],
+                    entry("Expired", &[]).unwrap(),
+                    WriteFlags::UPSERT,
+                )
+                .unwrap();
+            tx.inner
+                .put(
+                    index.dbi(),
+                    0u64.to_be_bytes(),
+                    [0u8, 9, b'E'],
+                    WriteFlags::UPSERT,
+                )
+                .unwrap();
+
+            // A row too short to hold its deadline is kept.
+            let expired = tx.inner.open_db(Some("Expired")).unwrap();
+            tx.inner
+                .put(expired.dbi(), 2u64.encode(), [1u8], WriteFlags::UPSERT)
+                .unwrap();
+            track(&tx.inner, "Expired", 0, &2u64.encode())
+        })
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(db.write(|tx| tx.reap_expired(10)).unwrap().unwrap(), 4);
+        db.read(|tx| {
+            assert_eq!(tx.entries::<Expired>().unwrap(), 1);
+            assert_eq!(tx.entries::<Live>().unwrap(), 1);
+            assert_eq!(tracked(tx), 1);
+        })
+        .unwrap();
+        assert_eq!(db.write(|tx| tx.reap_expired(10)).unwrap().unwrap(), 0);
+    }
+}
@@ -1,12 +1,10 @@
 //! Module that interacts with MDBX.
 
 use libmdbx_native::{
-    Database, DatabaseFlags, Environment, EnvironmentFlags, Geometry, HandleSlowReadersReturnCode,
-    MaxReadTransactionDuration, Mode, PageSize, RO, RW, SyncMode,
+    Database, DatabaseFlags, Environment, EnvironmentFlags, Geometry, MaxReadTransactionDuration,
+    Mode, PageSize, RO, RW, SyncMode,
 };
 
-use libmdbx_native::ffi;
-
 use reth_db::{
     ClientVersion, DatabaseError,
     lockfile::StorageLock,
@@ -22,9 +20,15 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use crate::{TableSet, tables};
+#[cfg(feature = "metrics")]
+use crate::metrics::{MetricsSink, TxMetrics};
+use crate::{TableSet, registry::TableRegistry, tables};
 
-use super::{LibmdbxTx, tx};
+use super::{
+    LibmdbxTx, ReadTxnPool,
+    slow_readers::{self, SlowReaderPolicy, SlowReaders},
+    tx,
+};
 
 /// 1 KB in bytes
 pub const KILOBYTE: usize = 1024;
@@ -38,10 +42,6 @@ pub const TERABYTE: usize = GIGABYTE * 1024;
 /// MDBX allows up to 32767 readers (`MDBX_READERS_LIMIT`), but we limit it to slightly below that
 const DEFAULT_MAX_READERS: u64 = 32_000;
 
-/// Space that a read-only transaction can occupy until the warning is emitted.
-/// See [`reth_libmdbx::EnvironmentBuilder::set_handle_slow_readers`] for more information.
-const MAX_SAFE_READER_SPACE: usize = 10 * GIGABYTE;
-
 /// Environment used when opening a MDBX environment. RO/RW.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum DatabaseEnvKind {
@@ -90,6 +90,13 @@ pub struct DatabaseArguments {
     ///
     /// This flag affects only at environment opening but can't be changed after.
     exclusive: Option<bool>,
+    /// What to do when a read transaction keeps the database from reusing pages.
+    slow_reader_policy: SlowReaderPolicy,
+    /// Maximum number of read transactions parked for reuse, no transaction is parked when zero.
+    read_txn_pool_size: usize,
+    /// Sink receiving the measurements of the transactions. If [None], nothing is measured.
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<dyn MetricsSink>>,
 }
 
 impl Default for DatabaseArguments {
@@ -112,6 +119,10 @@ impl DatabaseArguments {
             log_level: None,
             max_read_transaction_duration: None,
             exclusive: None,
+            slow_reader_policy: SlowReaderPolicy::Log,
+            read_txn_pool_size: 0,
+            #[cfg(feature = "metrics")]
+            metrics: None,
         }
     }
 
@@ -152,6 +163,29 @@ impl DatabaseArguments {
         self
     }
 
+    /// Set the policy applied to the read transactions that keep the database from reusing pages.
+    pub const fn with_slow_reader_policy(mut self, slow_reader_policy: SlowReaderPolicy) -> Self {
+        self.slow_reader_policy = slow_reader_policy;
+        self
+    }
+
+    /// Set the number of read transactions kept parked with `mdbx_txn_reset` once the reads of
+    /// [`LibmdbxProvider`](crate::LibmdbxProvider) end, so that later reads renew them instead of
+    /// beginning new ones.
+    ///
+    /// Every parked transaction keeps a reader slot, disabled by default.
+    pub const fn with_read_txn_pool_size(mut self, read_txn_pool_size: usize) -> Self {
+        self.read_txn_pool_size = read_txn_pool_size;
+        self
+    }
+
+    /// Set the sink receiving the measurements of the transactions.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Option<Arc<dyn MetricsSink>>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
     /// Returns the client version if any.
     pub const fn client_version(&self) -> &ClientVersion {
         &self.client_version
@@ -165,6 +199,15 @@ pub struct DatabaseEnv {
     inner: Environment,
     /// Write lock for when dealing with a read-write environment.
     _lock_file: Option<StorageLock>,
+    /// Per-table behaviour applied by the transactions.
+    registry: Arc<TableRegistry>,
+    /// Slow reader policy and counters, shared with the slow readers callback.
+    slow_readers: Arc<SlowReaders>,
+    /// Read transactions parked for reuse.
+    read_pool: ReadTxnPool,
+    /// Sink receiving the measurements of the transactions.
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<dyn MetricsSink>>,
 }
 
 impl reth_db::Database for DatabaseEnv {
@@ -216,40 +259,7 @@ impl DatabaseEnv {
         inner_env.set_max_dbs(256);
         inner_env.set_geometry(args.geometry);
 
-        fn is_current_process(id: u32) -> bool {
-            #[cfg(unix)]
-            {
-                id == std::os::unix::process::parent_id() || id == std::process::id()
-            }
-
-            #[cfg(not(unix))]
-            {
-                id == std::process::id()
-            }
-        }
-
-        extern "C" fn handle_slow_readers(
-            _env: *const ffi::MDBX_env,
-            _txn: *const ffi::MDBX_txn,
-            process_id: ffi::mdbx_pid_t,
-            thread_id: ffi::mdbx_tid_t,
-            read_txn_id: u64,
-            gap: std::ffi::c_uint,
-            space: usize,
-            retry: std::ffi::c_int,
-        ) -> HandleSlowReadersReturnCode {
-            if space > MAX_SAFE_READER_SPACE {
-                let message = if is_current_process(process_id as u32) {
-                    "Current process has a long-lived database transaction that grows the database file."
-                } else {
-                    "External process has a long-lived database transaction that grows the database file. \
-                     Use shorter-lived read transactions or shut down the node."
-                };
-            }
-
-            libmdbx_native::HandleSlowReadersReturnCode::ProceedWithoutKillingReader
-        }
-        inner_env.set_handle_slow_readers(handle_slow_readers);
+        inner_env.set_handle_slow_readers(slow_readers::handle_slow_readers);
 
         inner_env.set_flags(EnvironmentFlags {
             mode,
@@ -319,17 +329,59 @@ impl DatabaseEnv {
             inner_env.set_max_read_transaction_duration(max_read_transaction_duration);
         }
 
-        let env = Self {
-            inner: inner_env
-                .open(path)
-                .map_err(|e| DatabaseError::Open(e.into()))?,
+        let inner = inner_env
+            .open(path)
+            .map_err(|e| DatabaseError::Open(e.into()))?;
+        let slow_readers =
+            inner.with_raw_env_ptr(|ptr| slow_readers::register(ptr, args.slow_reader_policy));
 
+        let env = Self {
+            inner,
             _lock_file,
+            registry: Default::default(),
+            slow_readers,
+            read_pool: ReadTxnPool::new(args.read_txn_pool_size),
+            #[cfg(feature = "metrics")]
+            metrics: args.metrics,
         };
 
         Ok(env)
     }
 
+    /// Sets the [`TableRegistry`] handed to every transaction opened on this environment.
+    pub(crate) fn with_table_registry(mut self, registry: TableRegistry) -> Self {
+        self.registry = Arc::new(registry);
+        self
+    }
+
+    /// Returns the [`TableRegistry`] of this environment.
+    pub(crate) fn table_registry(&self) -> &Arc<TableRegistry> {
+        &self.registry
+    }
+
+    /// Returns the pool of parked read transactions of this environment.
+    pub(crate) fn read_pool(&self) -> &ReadTxnPool {
+        &self.read_pool
+    }
+
+    /// Starts measuring a new transaction, if a [`MetricsSink`] is set.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn tx_metrics(&self, read_only: bool) -> Option<TxMetrics> {
+        self.metrics
+            .as_ref()
+            .map(|sink| TxMetrics::new(sink.clone(), read_only))
+    }
+
+    /// Returns the number of times a slow reader kept the database from reusing pages.
+    pub fn slow_reader_events(&self) -> u64 {
+        self.slow_readers.events()
+    }
+
+    /// Returns the number of reader slots cleared by the [`SlowReaderPolicy`].
+    pub fn cleared_slow_readers(&self) -> u64 {
+        self.slow_readers.cleared()
+    }
+
     // /// Creates all the tables defined in [`Tables`], if necessary.
     // pub fn create_tables(&self) -> Result<(), DatabaseError> {
     //     self.create_tables_for::<Tables>()
@@ -359,6 +411,12 @@ impl DatabaseEnv {
     // }
 }
 
+impl Drop for DatabaseEnv {
+    fn drop(&mut self) {
+        self.inner.with_raw_env_ptr(slow_readers::unregister);
+    }
+}
+
 impl Deref for DatabaseEnv {
     type Target = Environment;
 
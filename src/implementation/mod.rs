@@ -1,8 +1,19 @@
 #![allow(unused)]
 
 mod native;
-pub use native::tx::LibmdbxTx;
 pub(crate) use native::*;
+pub use native::{
+    cursor::{LibmdbxCursor, RenewingWalker},
+    dyn_table::{DynDecoder, DynTable},
+    tx::LibmdbxTx,
+};
 
 mod env;
+pub use env::DatabaseArguments;
 pub(crate) use env::*;
+
+mod read_pool;
+pub(crate) use read_pool::ReadTxnPool;
+
+mod slow_readers;
+pub use slow_readers::SlowReaderPolicy;
@@ -2,9 +2,10 @@ use std::{
     borrow::Cow,
     marker::PhantomData,
     ops::{Bound, RangeBounds},
+    time::Duration,
 };
 
-use libmdbx_native::{Error, RW, TransactionKind, WriteFlags};
+use libmdbx_native::{Error, RO, RW, TransactionKind, WriteFlags};
 use reth_db::{
     DatabaseError, DatabaseWriteOperation,
     common::{PairResult, ValueOnlyResult},
@@ -12,11 +13,16 @@ use reth_db::{
         DbCursorRO, DbCursorRW, DbDupCursorRO, DbDupCursorRW, DupWalker, RangeWalker,
         ReverseWalker, Walker,
     },
-    table::{DupSort, Encode, Table},
+    table::{DupSort, Encode, Table, TableRow},
 };
 use reth_storage_errors::db::DatabaseWriteError;
 
-use super::utils::{decode_one, decode_value, decoder, uncompressable_ref_util};
+use super::utils::{compress_value, decode_one, decode_value, decoder, encode_key, encode_row};
+use crate::{
+    error::{self, ErrorContext, Operation},
+    expiry,
+    trace::{self, Access},
+};
 
 #[macro_export]
 macro_rules! decode {
@@ -32,20 +38,370 @@ macro_rules! decode {
 pub struct LibmdbxCursor<T: Table, K: TransactionKind> {
     /// Inner `libmdbx` cursor.
     pub(crate) inner: libmdbx_native::Cursor<K>,
+    /// Time-to-live of the rows if `T` is an expiring table.
+    ttl: Option<Duration>,
+    /// Stored key and value ending the last page returned by
+    /// [`LibmdbxCursor::next_page_of_dups`].
+    dup_page_end: Option<(Vec<u8>, Vec<u8>)>,
     /// Phantom data to enforce encoding/decoding.
     _dbi: PhantomData<T>,
 }
 
+/// Where to move the cursor when it lands on an expired row.
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Next,
+    Prev,
+    Stop,
+}
+
 impl<T: Table, K: TransactionKind> LibmdbxCursor<T, K> {
-    pub(crate) fn new(inner: libmdbx_native::Cursor<K>) -> Self {
+    pub(crate) fn new(inner: libmdbx_native::Cursor<K>, ttl: Option<Duration>) -> Self {
         Self {
             inner,
+            ttl,
+            dup_page_end: None,
             _dbi: PhantomData,
         }
     }
 
+    /// Estimates the number of rows between this cursor and `other` without reading them,
+    /// negative if `other` is positioned before this cursor.
+    pub fn estimate_distance(&self, other: &Self) -> Result<isize, DatabaseError> {
+        self.inner
+            .estimate_distance(&other.inner)
+            .map_err(|e| DatabaseError::Read(e.into()))
+    }
+
     pub fn seek_raw(&mut self, key: &[u8]) -> PairResult<T> {
-        decode!(self.inner.set_key(key))
+        let row = self.inner.set_key(key);
+        self.decode_row(row, Step::Stop)
+    }
+
+    /// Decodes the row the cursor landed on. For expiring tables the deadline is stripped and
+    /// expired rows are skipped in the direction of `step`.
+    fn decode_row<'a>(
+        &mut self,
+        mut row: Result<Option<(Cow<'a, [u8]>, Cow<'a, [u8]>)>, Error>,
+        step: Step,
+    ) -> PairResult<T> {
+        if let Ok(Some((key, value))) = &row {
+            trace::record_sizes(key.len(), Some(value.len()));
+        }
+
+        loop {
+            let Some((key, value)) = row.map_err(|e| DatabaseError::Read(e.into()))? else {
+                return Ok(None);
+            };
+
+            if self.ttl.is_none() {
+                return Self::decode_pair(key, value).map(Some);
+            }
+
+            let (deadline, value) = expiry::strip(value)?;
+            if !expiry::is_expired(deadline) {
+                return Self::decode_pair(key, value).map(Some);
+            }
+
+            row = match step {
+                Step::Next => self.inner.next(),
+                Step::Prev => self.inner.prev(),
+                Step::Stop => return Ok(None),
+            };
+        }
+    }
+
+    /// Decodes a `(key, value)` pair, reporting the table and the key if it is invalid.
+    fn decode_pair(key: Cow<'_, [u8]>, value: Cow<'_, [u8]>) -> Result<TableRow<T>, DatabaseError> {
+        decoder::<T>((Cow::Borrowed(&key), value)).map_err(|e| {
+            let context = ErrorContext::new(Operation::Get)
+                .with_table(T::NAME)
+                .with_key(&key);
+            error::Error::Codec(e.into(), context).into()
+        })
+    }
+}
+
+impl<T: Table> LibmdbxCursor<T, RW> {
+    /// Prefixes the value with its deadline and tracks the row if `T` is an expiring table.
+    fn stamp(&self, key: &[u8], value: Vec<u8>) -> Result<Vec<u8>, DatabaseError> {
+        let Some(ttl) = self.ttl else {
+            return Ok(value);
+        };
+
+        let deadline = expiry::deadline(ttl);
+        expiry::track(self.inner.txn(), T::NAME, deadline, key)?;
+
+        Ok(expiry::stamp(deadline, &value))
+    }
+}
+
+impl<T: DupSort, K: TransactionKind> LibmdbxCursor<T, K> {
+    /// Returns the next page of duplicates of the current key, decoded at once, for tables
+    /// created with [`DatabaseFlags::DUP_FIXED`](libmdbx_native::DatabaseFlags::DUP_FIXED).
+    ///
+    /// Once the cursor is positioned on a key, with [`DbCursorRO::seek_exact`] for example, the
+    /// first call returns the whole page holding the current duplicate. Returns [None] once every
+    /// duplicate of the key was returned.
+    pub fn next_page_of_dups(&mut self) -> Result<Option<Vec<T::Value>>, DatabaseError> {
+        trace::traced(T::NAME, "cursor_next_page_of_dups", Access::Read, || {
+            let read = |e: Error| DatabaseError::Read(e.into());
+
+            let Some((key, value)) = self
+                .inner
+                .get_current::<Cow<'_, [u8]>, Cow<'_, [u8]>>()
+                .map_err(read)?
+            else {
+                return Ok(None);
+            };
+            let continues = self
+                .dup_page_end
+                .as_ref()
+                .is_some_and(|(end_key, end_value)| {
+                    key.as_ref() == end_key.as_slice() && value.as_ref() == end_value.as_slice()
+                });
+
+            let page = if continues {
+                self.inner
+                    .next_multiple::<(), Cow<'_, [u8]>>()
+                    .map_err(read)?
+                    .map(|(_, page)| page)
+            } else {
+                self.inner.get_multiple::<Cow<'_, [u8]>>().map_err(read)?
+            };
+            let Some(page) = page else {
+                self.dup_page_end = None;
+                return Ok(None);
+            };
+
+            // The cursor stands on the last duplicate of the page, which tells their size.
+            let Some((key, last)) = self
+                .inner
+                .get_current::<Cow<'_, [u8]>, Cow<'_, [u8]>>()
+                .map_err(read)?
+            else {
+                return Ok(None);
+            };
+            trace::record_sizes(key.len(), Some(page.len()));
+
+            let mut values = Vec::with_capacity(page.len() / last.len().max(1));
+            for stored in page.chunks(last.len().max(1)) {
+                values.push(decode_one::<T>(Cow::Borrowed(stored)).map_err(|e| {
+                    let context = ErrorContext::new(Operation::Get)
+                        .with_table(T::NAME)
+                        .with_key(&key);
+                    DatabaseError::from(error::Error::Codec(e.into(), context))
+                })?);
+            }
+            self.dup_page_end = Some((key.into_owned(), last.into_owned()));
+
+            Ok(Some(values))
+        })
+    }
+}
+
+impl<T: DupSort> LibmdbxCursor<T, RW> {
+    /// Stores many duplicates of `key` in a single call with `MDBX_MULTIPLE`, for tables created
+    /// with [`DatabaseFlags::DUP_FIXED`](libmdbx_native::DatabaseFlags::DUP_FIXED).
+    ///
+    /// Every value must compress to the same size. Returns the number of stored duplicates.
+    pub fn put_dups(
+        &mut self,
+        key: T::Key,
+        values: impl IntoIterator<Item = T::Value>,
+    ) -> Result<usize, DatabaseError> {
+        trace::traced(T::NAME, "cursor_put_dups", Access::Write, || {
+            let operation = DatabaseWriteOperation::CursorUpsert;
+            let key = encode_key::<T>(operation, key)?;
+            let values = values
+                .into_iter()
+                .map(|value| compress_value::<T>(operation, &key, &value))
+                .collect::<Result<Vec<_>, _>>()?;
+            let write_error = |key: Vec<u8>, e: Error| -> DatabaseError {
+                DatabaseWriteError {
+                    info: e.into(),
+                    operation,
+                    table_name: T::NAME,
+                    key,
+                }
+                .into()
+            };
+
+            let mut buf = Vec::new();
+            let mut value_size = None;
+            for value in values {
+                let value = self.stamp(&key, value)?;
+                if *value_size.get_or_insert(value.len()) != value.len() {
+                    return Err(write_error(key, Error::BadValSize));
+                }
+                buf.extend_from_slice(&value);
+            }
+            let Some(value_size) = value_size else {
+                return Ok(0);
+            };
+            trace::record_sizes(key.len(), Some(buf.len()));
+
+            self.inner
+                .put_multiple(&key, &buf, value_size, WriteFlags::empty())
+                .map_err(|e| write_error(key, e))
+        })
+    }
+}
+
+impl<T: Table> LibmdbxCursor<T, RO> {
+    /// Walks the table from `start_key` like [`DbCursorRO::walk`], renewing the transaction when
+    /// the read transaction timeout ends it and resuming after the last returned row, so that
+    /// scans can outlive [`MaxReadTransactionDuration`](libmdbx_native::MaxReadTransactionDuration).
+    ///
+    /// The scan is **not** a consistent snapshot of the table: every renewal moves the
+    /// transaction to the latest snapshot, so the rows after the last returned one reflect the
+    /// writes committed in the meantime. Other cursors of the transaction have to be reopened
+    /// after a renewal.
+    pub fn renewing_walk(
+        &mut self,
+        start_key: Option<T::Key>,
+    ) -> Result<RenewingWalker<'_, T>, DatabaseError> {
+        Ok(RenewingWalker {
+            start: start_key
+                .map(|key| encode_key::<T>(Operation::Cursor, key))
+                .transpose()?,
+            last: None,
+            done: false,
+            cursor: self,
+        })
+    }
+
+    /// Moves to the row after `last`, or to the first row at or after `start` if no row was
+    /// returned yet, renewing the transaction once if it timed out.
+    fn renewing_next(
+        &mut self,
+        start: Option<&[u8]>,
+        last: Option<&(Vec<u8>, Vec<u8>)>,
+    ) -> PairResult<T> {
+        let row = match last {
+            Some(_) => self.inner.next(),
+            None => self.seek_start(start),
+        };
+
+        match self.decode_row(row, Step::Next) {
+            Err(e) if is_timed_out(&e) => {
+                self.renew()?;
+                let row = match last {
+                    Some((key, value)) => self.resume_after(key, value),
+                    None => self.seek_start(start),
+                };
+                self.decode_row(row, Step::Next)
+            }
+            result => result,
+        }
+    }
+
+    fn seek_start<'a>(
+        &mut self,
+        start: Option<&[u8]>,
+    ) -> Result<Option<(Cow<'a, [u8]>, Cow<'a, [u8]>)>, Error> {
+        match start {
+            Some(key) => self.inner.set_range(key),
+            None => self.inner.first(),
+        }
+    }
+
+    /// Positions the cursor on the first row after the stored `(key, value)`, which may have been
+    /// deleted since.
+    fn resume_after<'a>(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<(Cow<'a, [u8]>, Cow<'a, [u8]>)>, Error> {
+        if T::DUPSORT {
+            if let Some(found) = self.inner.get_both_range::<Cow<'_, [u8]>>(key, value)? {
+                return if found.as_ref() == value {
+                    self.inner.next()
+                } else {
+                    self.inner.get_current()
+                };
+            }
+        }
+
+        match self.inner.set_range::<Cow<'_, [u8]>, Cow<'_, [u8]>>(key)? {
+            Some((found, _)) if found.as_ref() == key => {
+                if T::DUPSORT {
+                    self.inner.next_nodup()
+                } else {
+                    self.inner.next()
+                }
+            }
+            row => Ok(row),
+        }
+    }
+
+    /// Renews the transaction timed out by the read transaction timeout, and the cursor with it.
+    fn renew(&mut self) -> Result<(), DatabaseError> {
+        self.inner
+            .txn()
+            .renew_timed_out()
+            .and_then(|_| self.inner.renew())
+            .map_err(|e| error::Error::Mdbx(e, ErrorContext::new(Operation::BeginTx)).into())
+    }
+}
+
+/// Returns `true` if `error` comes from a read transaction ended by the read transaction timeout.
+fn is_timed_out(error: &DatabaseError) -> bool {
+    matches!(
+        error,
+        DatabaseError::Read(info) if info.code == Error::ReadTransactionTimeout.to_err_code()
+    )
+}
+
+/// Iterator over the rows of a table surviving the read transaction timeout, see
+/// [`LibmdbxCursor::renewing_walk`].
+#[derive(Debug)]
+pub struct RenewingWalker<'c, T: Table> {
+    cursor: &'c mut LibmdbxCursor<T, RO>,
+    /// Encoded key the walk starts at.
+    start: Option<Vec<u8>>,
+    /// Stored key and value of the last returned row, the value only for DUPSORT tables.
+    last: Option<(Vec<u8>, Vec<u8>)>,
+    done: bool,
+}
+
+impl<T: Table> Iterator for RenewingWalker<'_, T> {
+    type Item = Result<TableRow<T>, DatabaseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let row = self
+            .cursor
+            .renewing_next(self.start.as_deref(), self.last.as_ref())
+            .and_then(|row| {
+                let Some(row) = row else {
+                    return Ok(None);
+                };
+
+                // Remember where the cursor stands, expired rows may have been skipped.
+                let current = self
+                    .cursor
+                    .inner
+                    .get_current::<Cow<'_, [u8]>, Cow<'_, [u8]>>()
+                    .map_err(|e| DatabaseError::Read(e.into()))?;
+                self.last = current.map(|(key, value)| {
+                    let value = if T::DUPSORT {
+                        value.into_owned()
+                    } else {
+                        Vec::new()
+                    };
+                    (key.into_owned(), value)
+                });
+
+                Ok(Some(row))
+            })
+            .transpose();
+
+        self.done = !matches!(row, Some(Ok(_)));
+        row
     }
 }
 
@@ -53,39 +409,59 @@ impl<T: Table, K: TransactionKind> LibmdbxCursor<T, K> {
 
 impl<T: Table, K: TransactionKind> DbCursorRO<T> for LibmdbxCursor<T, K> {
     fn first(&mut self) -> PairResult<T> {
-        decode!(self.inner.first())
+        trace::traced(T::NAME, "cursor_first", Access::Read, || {
+            let row = self.inner.first();
+            self.decode_row(row, Step::Next)
+        })
     }
 
     fn seek_exact(&mut self, key: <T as Table>::Key) -> PairResult<T> {
-        decode!(self.inner.set_key(key.encode().as_ref()))
+        trace::traced(T::NAME, "cursor_seek_exact", Access::Read, || {
+            let key = encode_key::<T>(Operation::Cursor, key)?;
+            let row = self.inner.set_key(&key);
+            self.decode_row(row, Step::Stop)
+        })
     }
 
     fn seek(&mut self, key: <T as Table>::Key) -> PairResult<T> {
-        decode!(self.inner.set_range(key.encode().as_ref()))
+        trace::traced(T::NAME, "cursor_seek", Access::Read, || {
+            let key = encode_key::<T>(Operation::Cursor, key)?;
+            let row = self.inner.set_range(&key);
+            self.decode_row(row, Step::Next)
+        })
     }
 
     fn next(&mut self) -> PairResult<T> {
-        decode!(self.inner.next())
+        trace::traced(T::NAME, "cursor_next", Access::Read, || {
+            let row = self.inner.next();
+            self.decode_row(row, Step::Next)
+        })
     }
 
     fn prev(&mut self) -> PairResult<T> {
-        decode!(self.inner.prev())
+        trace::traced(T::NAME, "cursor_prev", Access::Read, || {
+            let row = self.inner.prev();
+            self.decode_row(row, Step::Prev)
+        })
     }
 
     fn last(&mut self) -> PairResult<T> {
-        decode!(self.inner.last())
+        trace::traced(T::NAME, "cursor_last", Access::Read, || {
+            let row = self.inner.last();
+            self.decode_row(row, Step::Prev)
+        })
     }
 
     fn current(&mut self) -> PairResult<T> {
-        decode!(self.inner.get_current())
+        trace::traced(T::NAME, "cursor_current", Access::Read, || {
+            let row = self.inner.get_current();
+            self.decode_row(row, Step::Stop)
+        })
     }
 
     fn walk(&mut self, start_key: Option<T::Key>) -> Result<Walker<'_, T, Self>, DatabaseError> {
         let start = if let Some(start_key) = start_key {
-            self.inner
-                .set_range(start_key.encode().as_ref())
-                .map_err(|e| DatabaseError::Read(e.into()))?
-                .map(decoder::<T>)
+            self.seek(start_key)?.map(Ok)
         } else {
             self.first().transpose()
         };
@@ -98,14 +474,13 @@ impl<T: Table, K: TransactionKind> DbCursorRO<T> for LibmdbxCursor<T, K> {
         range: impl RangeBounds<T::Key>,
     ) -> Result<RangeWalker<'_, T, Self>, DatabaseError> {
         let start = match range.start_bound().cloned() {
-            Bound::Included(key) => self.inner.set_range(key.encode().as_ref()),
+            Bound::Included(key) => self.seek(key),
             Bound::Excluded(_key) => {
                 unreachable!("Rust doesn't allow for Bound::Excluded in starting bounds");
             }
-            Bound::Unbounded => self.inner.first(),
-        }
-        .map_err(|e| DatabaseError::Read(e.into()))?
-        .map(decoder::<T>);
+            Bound::Unbounded => self.first(),
+        }?
+        .map(Ok);
 
         Ok(RangeWalker::new(self, start, range.end_bound().cloned()))
     }
@@ -115,7 +490,7 @@ impl<T: Table, K: TransactionKind> DbCursorRO<T> for LibmdbxCursor<T, K> {
         start_key: Option<T::Key>,
     ) -> Result<ReverseWalker<'_, T, Self>, DatabaseError> {
         let start = if let Some(start_key) = start_key {
-            decode!(self.inner.set_range(start_key.encode().as_ref()))
+            self.seek(start_key)
         } else {
             self.last()
         }
@@ -128,12 +503,16 @@ impl<T: Table, K: TransactionKind> DbCursorRO<T> for LibmdbxCursor<T, K> {
 impl<T: DupSort, K: TransactionKind> DbDupCursorRO<T> for LibmdbxCursor<T, K> {
     /// Returns the next `(key, value)` pair of a DUPSORT table.
     fn next_dup(&mut self) -> PairResult<T> {
-        decode!(self.inner.next_dup())
+        trace::traced(T::NAME, "cursor_next_dup", Access::Read, || {
+            decode!(self.inner.next_dup())
+        })
     }
 
     /// Returns the next `(key, value)` pair skipping the duplicates.
     fn next_no_dup(&mut self) -> PairResult<T> {
-        decode!(self.inner.next_nodup())
+        trace::traced(T::NAME, "cursor_next_no_dup", Access::Read, || {
+            decode!(self.inner.next_nodup())
+        })
     }
 
     /// Returns the next `value` of a duplicate `key`.
@@ -150,8 +529,9 @@ impl<T: DupSort, K: TransactionKind> DbDupCursorRO<T> for LibmdbxCursor<T, K> {
         key: <T as Table>::Key,
         subkey: <T as DupSort>::SubKey,
     ) -> ValueOnlyResult<T> {
+        let key = encode_key::<T>(Operation::Cursor, key)?;
         self.inner
-            .get_both_range(key.encode().as_ref(), subkey.encode().as_ref())
+            .get_both_range(&key, subkey.encode().as_ref())
             .map_err(|e| DatabaseError::Read(e.into()))?
             .map(decode_one::<T>)
             .transpose()
@@ -171,14 +551,14 @@ impl<T: DupSort, K: TransactionKind> DbDupCursorRO<T> for LibmdbxCursor<T, K> {
         let start = match (key, subkey) {
             (Some(key), Some(subkey)) => {
                 // encode key and decode it after.
-                let key: Vec<u8> = key.encode().into();
+                let key = encode_key::<T>(Operation::Cursor, key)?;
                 self.inner
                     .get_both_range(key.as_ref(), subkey.encode().as_ref())
                     .map_err(|e| DatabaseError::Read(e.into()))?
                     .map(|val| decoder::<T>((Cow::Owned(key), val)))
             }
             (Some(key), None) => {
-                let key: Vec<u8> = key.encode().into();
+                let key = encode_key::<T>(Operation::Cursor, key)?;
                 self.inner
                     .set(key.as_ref())
                     .map_err(|e| DatabaseError::Read(e.into()))?
@@ -186,7 +566,7 @@ impl<T: DupSort, K: TransactionKind> DbDupCursorRO<T> for LibmdbxCursor<T, K> {
             }
             (None, Some(subkey)) => {
                 if let Some((key, _)) = self.first()? {
-                    let key: Vec<u8> = key.encode().into();
+                    let key = encode_key::<T>(Operation::Cursor, key)?;
                     self.inner
                         .get_both_range(key.as_ref(), subkey.encode().as_ref())
                         .map_err(|e| DatabaseError::Read(e.into()))?
@@ -217,79 +597,235 @@ impl<T: Table> DbCursorRW<T> for LibmdbxCursor<T, RW> {
     /// need to `seek_exact` & `delete_current` if the key+subkey was found,
     /// before calling `upsert`.
     fn upsert(&mut self, key: T::Key, value: &T::Value) -> Result<(), DatabaseError> {
-        let (key, value) = uncompressable_ref_util::<T>(key, value);
-        self.inner
-            .put(&key, &value, WriteFlags::UPSERT)
-            .map_err(|e| {
-                DatabaseWriteError {
-                    info: e.into(),
-                    operation: DatabaseWriteOperation::CursorUpsert,
-                    table_name: T::NAME,
-                    key,
-                }
-                .into()
-            })
+        trace::traced(T::NAME, "cursor_upsert", Access::Write, || {
+            let (key, value) = encode_row::<T>(DatabaseWriteOperation::CursorUpsert, key, value)?;
+            let value = self.stamp(&key, value)?;
+            trace::record_sizes(key.len(), Some(value.len()));
+            self.inner
+                .put(&key, &value, WriteFlags::UPSERT)
+                .map_err(|e| {
+                    DatabaseWriteError {
+                        info: e.into(),
+                        operation: DatabaseWriteOperation::CursorUpsert,
+                        table_name: T::NAME,
+                        key,
+                    }
+                    .into()
+                })
+        })
     }
 
     fn insert(&mut self, key: T::Key, value: &T::Value) -> Result<(), DatabaseError> {
-        let (key, value) = uncompressable_ref_util::<T>(key, value);
-        self.inner
-            .put(&key, &value, WriteFlags::NO_OVERWRITE)
-            .map_err(|e| {
-                DatabaseWriteError {
-                    info: e.into(),
-                    operation: DatabaseWriteOperation::CursorInsert,
-                    table_name: T::NAME,
-                    key,
-                }
-                .into()
-            })
+        trace::traced(T::NAME, "cursor_insert", Access::Write, || {
+            let (key, value) = encode_row::<T>(DatabaseWriteOperation::CursorInsert, key, value)?;
+            let value = self.stamp(&key, value)?;
+            trace::record_sizes(key.len(), Some(value.len()));
+            self.inner
+                .put(&key, &value, WriteFlags::NO_OVERWRITE)
+                .map_err(|e| {
+                    DatabaseWriteError {
+                        info: e.into(),
+                        operation: DatabaseWriteOperation::CursorInsert,
+                        table_name: T::NAME,
+                        key,
+                    }
+                    .into()
+                })
+        })
     }
 
     /// Appends the data to the end of the table. Consequently, the append
     /// operation will fail if the inserted key is less than the last table
     /// key
     fn append(&mut self, key: T::Key, value: &T::Value) -> Result<(), DatabaseError> {
-        let (key, value) = uncompressable_ref_util::<T>(key, value);
-        self.inner
-            .put(&key, &value, WriteFlags::APPEND)
-            .map_err(|e| {
-                DatabaseWriteError {
-                    info: e.into(),
-                    operation: DatabaseWriteOperation::CursorAppend,
-                    table_name: T::NAME,
-                    key,
-                }
-                .into()
-            })
+        trace::traced(T::NAME, "cursor_append", Access::Write, || {
+            let (key, value) = encode_row::<T>(DatabaseWriteOperation::CursorAppend, key, value)?;
+            let value = self.stamp(&key, value)?;
+            trace::record_sizes(key.len(), Some(value.len()));
+            self.inner
+                .put(&key, &value, WriteFlags::APPEND)
+                .map_err(|e| {
+                    DatabaseWriteError {
+                        info: e.into(),
+                        operation: DatabaseWriteOperation::CursorAppend,
+                        table_name: T::NAME,
+                        key,
+                    }
+                    .into()
+                })
+        })
     }
 
     fn delete_current(&mut self) -> Result<(), DatabaseError> {
-        self.inner
-            .del(WriteFlags::CURRENT)
-            .map_err(|e| DatabaseError::Delete(e.into()))
+        trace::traced(T::NAME, "cursor_delete_current", Access::Write, || {
+            self.inner
+                .del(WriteFlags::CURRENT)
+                .map_err(|e| DatabaseError::Delete(e.into()))
+        })
     }
 }
 
 impl<T: DupSort> DbDupCursorRW<T> for LibmdbxCursor<T, RW> {
     fn delete_current_duplicates(&mut self) -> Result<(), DatabaseError> {
-        self.inner
-            .del(WriteFlags::NO_DUP_DATA)
-            .map_err(|e| DatabaseError::Delete(e.into()))
+        trace::traced(
+            T::NAME,
+            "cursor_delete_current_duplicates",
+            Access::Write,
+            || {
+                self.inner
+                    .del(WriteFlags::NO_DUP_DATA)
+                    .map_err(|e| DatabaseError::Delete(e.into()))
+            },
+        )
     }
 
     fn append_dup(&mut self, key: T::Key, value: T::Value) -> Result<(), DatabaseError> {
-        let (key, value) = uncompressable_ref_util::<T>(key, &value);
-        self.inner
-            .put(&key, &value, WriteFlags::APPEND_DUP)
-            .map_err(|e| {
-                DatabaseWriteError {
-                    info: e.into(),
-                    operation: DatabaseWriteOperation::CursorAppendDup,
-                    table_name: T::NAME,
-                    key,
+        trace::traced(T::NAME, "cursor_append_dup", Access::Write, || {
+            let (key, value) =
+                encode_row::<T>(DatabaseWriteOperation::CursorAppendDup, key, &value)?;
+            trace::record_sizes(key.len(), Some(value.len()));
+            self.inner
+                .put(&key, &value, WriteFlags::APPEND_DUP)
+                .map_err(|e| {
+                    DatabaseWriteError {
+                        info: e.into(),
+                        operation: DatabaseWriteOperation::CursorAppendDup,
+                        table_name: T::NAME,
+                        key,
+                    }
+                    .into()
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+
+    use libmdbx_native::MaxReadTransactionDuration;
+    use reth_db::{
+        ClientVersion,
+        transaction::{DbTx, DbTxMut},
+    };
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{DatabaseArguments, IntegerKey, LibmdbxProvider, LibmdbxTx, test_utils};
+
+    const MAX_DURATION: Duration = Duration::from_millis(100);
+
+    db_table!((Rows) | u64, IntegerKey<u64>);
+    db_table!((Dups, integer_key, integer_dup) | u64 => u64);
+    db_table!((Fixed, dup_fixed) | u64 => u64, IntegerKey<u64>);
+
+    tables!(WalkTables, 3, [Rows, Dups, Fixed]);
+
+    fn provider() -> (TempDir, LibmdbxProvider<WalkTables>) {
+        let args = DatabaseArguments::new(ClientVersion::default())
+            .with_log_level(None)
+            .with_max_read_transaction_duration(Some(MaxReadTransactionDuration::Set(
+                MAX_DURATION,
+            )));
+        test_utils::provider_with_args(args)
+    }
+
+    /// Outlives the read transaction timeout of `tx` and checks that it ended the transaction.
+    fn time_out(tx: &LibmdbxTx<RO>) {
+        sleep(MAX_DURATION * 10);
+        assert_eq!(tx.inner.id().err(), Some(Error::ReadTransactionTimeout));
+    }
+
+    #[test]
+    fn renewing_walk_resumes_after_timeout() {
+        let (_dir, db) = provider();
+        db.write(|tx| {
+            for key in 0..10 {
+                tx.put::<Rows>(key, IntegerKey(key))?;
+                for dup in 0..3 {
+                    tx.put::<Dups>(IntegerKey(key), IntegerKey(dup))?;
                 }
-                .into()
+            }
+            Ok::<_, DatabaseError>(())
+        })
+        .unwrap()
+        .unwrap();
+
+        let rows = db
+            .read(|tx| {
+                let mut cursor = tx.cursor_read::<Rows>().unwrap();
+                let mut rows = Vec::new();
+                for row in cursor.renewing_walk(Some(2)).unwrap() {
+                    let row = row.unwrap();
+                    if row.0 == 5 {
+                        time_out(tx);
+                    }
+                    rows.push(row);
+                }
+                rows
+            })
+            .unwrap();
+        assert_eq!(
+            rows,
+            (2..10)
+                .map(|key| (key, IntegerKey(key)))
+                .collect::<Vec<_>>()
+        );
+
+        let dups = db
+            .read(|tx| {
+                let mut cursor = tx.cursor_dup_read::<Dups>().unwrap();
+                let mut dups = Vec::new();
+                for row in cursor.renewing_walk(None).unwrap() {
+                    let (key, dup) = row.unwrap();
+                    if (key.0, dup.0) == (4, 1) {
+                        time_out(tx);
+                    }
+                    dups.push((key.0, dup.0));
+                }
+                dups
+            })
+            .unwrap();
+        let expected: Vec<_> = (0..10)
+            .flat_map(|key| (0..3).map(move |dup| (key, dup)))
+            .collect();
+        assert_eq!(dups, expected);
+    }
+
+    #[test]
+    fn fixed_dups_are_put_and_read_by_page() {
+        let (_dir, db) = test_utils::provider::<WalkTables>();
+        let mut values: Vec<u64> = (0..3000).collect();
+
+        let stored = db
+            .write(|tx| {
+                tx.put::<Fixed>(2, IntegerKey(7))?;
+                let mut cursor = tx.cursor_dup_write::<Fixed>()?;
+                assert_eq!(cursor.put_dups(1, Vec::<IntegerKey<u64>>::new())?, 0);
+                cursor.put_dups(1, values.iter().copied().map(IntegerKey))
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored, values.len());
+
+        let pages = db
+            .read(|tx| {
+                let mut cursor = tx.cursor_dup_read::<Fixed>().unwrap();
+                cursor.seek_exact(1).unwrap();
+                let mut pages = Vec::new();
+                while let Some(page) = cursor.next_page_of_dups().unwrap() {
+                    pages.push(page);
+                }
+                pages
             })
+            .unwrap();
+        assert!(pages.len() > 1);
+
+        // Duplicates are sorted by their stored bytes, and the next key isn't read.
+        values.sort_by_key(|value| value.to_ne_bytes());
+        assert_eq!(
+            pages.concat(),
+            values.into_iter().map(IntegerKey).collect::<Vec<_>>()
+        );
     }
 }
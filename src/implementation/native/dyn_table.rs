@@ -0,0 +1,258 @@
+use std::{collections::BTreeSet, fmt::Write};
+
+use libmdbx_native::{Cursor, RW, TransactionKind, WriteFlags};
+use parking_lot::Mutex;
+use reth_db::{
+    DatabaseError,
+    table::{Decode, Decompress, Table},
+};
+use reth_mdbx_sys::MDBX_dbi;
+
+use super::tx::LibmdbxTx;
+use crate::{
+    error::{self, ErrorContext, Operation},
+    expiry,
+};
+
+/// Formats an encoded key and a compressed value for display.
+pub type DynDecoder = fn(&[u8], &[u8]) -> Result<String, DatabaseError>;
+
+/// Decodes a row of `T` and formats it with [`Debug`](std::fmt::Debug).
+pub(crate) fn debug_decoder<T: Table>(key: &[u8], value: &[u8]) -> Result<String, DatabaseError> {
+    let key = <T::Key as Decode>::decode(key)?;
+    let value = <T::Value as Decompress>::decompress(value)?;
+
+    Ok(format!("{key:?} => {value:?}"))
+}
+
+/// Untyped handle to a table, looked up by name.
+///
+/// Keys and values are the raw bytes stored in the database: values of expiring tables keep their
+/// deadline prefix, and writes bypass secondary indexes and expiry tracking.
+#[derive(Debug)]
+pub struct DynTable<'tx, K: TransactionKind> {
+    tx: &'tx LibmdbxTx<K>,
+    name: String,
+    dbi: MDBX_dbi,
+    decoder: Option<DynDecoder>,
+}
+
+impl<'tx, K: TransactionKind> DynTable<'tx, K> {
+    pub(crate) fn new(tx: &'tx LibmdbxTx<K>, name: &str) -> Result<Self, DatabaseError> {
+        let dbi = tx
+            .inner
+            .open_db(Some(name))
+            .map_err(|e| DatabaseError::Open(e.into()))?
+            .dbi();
+
+        Ok(Self {
+            tx,
+            name: name.to_string(),
+            dbi,
+            decoder: tx.registry().decoder_of(name),
+        })
+    }
+
+    /// Name of the table.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Replaces the decoder used by [`DynTable::format`].
+    pub fn with_decoder(mut self, decoder: DynDecoder) -> Self {
+        self.decoder = Some(decoder);
+        self
+    }
+
+    /// Returns the raw value stored under `key`.
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DatabaseError> {
+        self.tx
+            .inner
+            .get(self.dbi, key)
+            .map_err(|e| DatabaseError::Read(e.into()))
+    }
+
+    /// Opens a raw cursor on the table.
+    pub fn cursor(&self) -> Result<Cursor<K>, DatabaseError> {
+        self.tx
+            .inner
+            .cursor_with_dbi(self.dbi)
+            .map_err(|e| DatabaseError::InitCursor(e.into()))
+    }
+
+    /// Calls `f` with every raw `(key, value)` pair of the table, in key order.
+    pub fn for_each(&self, mut f: impl FnMut(&[u8], &[u8])) -> Result<(), DatabaseError> {
+        let cursor = self.cursor()?;
+        for row in cursor.iter_slices() {
+            let (key, value) = row.map_err(|e| DatabaseError::Read(e.into()))?;
+            f(&key, &value);
+        }
+
+        Ok(())
+    }
+
+    /// Formats a raw row with the decoder registered for the table, falling back to hex when
+    /// there is none.
+    pub fn format(&self, key: &[u8], value: &[u8]) -> Result<String, DatabaseError> {
+        let Some(decoder) = self.decoder else {
+            return Ok(format!("{} => {}", hex(key), hex(value)));
+        };
+
+        format_with(
+            decoder,
+            self.tx.registry().ttl_of(&self.name).is_some(),
+            key,
+            value,
+        )
+    }
+}
+
+impl DynTable<'_, RW> {
+    /// Stores the raw `value` under `key`, replacing the current value of plain tables.
+    pub fn put(&self, key: &[u8], value: &[u8]) -> Result<(), DatabaseError> {
+        self.tx
+            .inner
+            .put(self.dbi, key, value, WriteFlags::UPSERT)
+            .map_err(|e| error::Error::Mdbx(e, self.context(Operation::Put, key)).into())
+    }
+
+    /// Deletes the row stored under `key`, only if its value equals `value` when given.
+    ///
+    /// Returns `true` if a row was deleted.
+    pub fn delete(&self, key: &[u8], value: Option<&[u8]>) -> Result<bool, DatabaseError> {
+        self.tx
+            .inner
+            .del(self.dbi, key, value)
+            .map_err(|e| error::Error::Mdbx(e, self.context(Operation::Delete, key)).into())
+    }
+
+    /// Context of an error of `operation` on the row stored under `key`.
+    fn context(&self, operation: Operation, key: &[u8]) -> ErrorContext {
+        ErrorContext::new(operation)
+            .with_table(static_name(&self.name))
+            .with_key(key)
+    }
+}
+
+/// Returns a `'static` copy of a table name, which the reth write errors require.
+///
+/// Names are only copied when an error is reported, and leaked once per table.
+fn static_name(name: &str) -> &'static str {
+    static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    let mut names = NAMES.lock();
+    if let Some(name) = names.get(name) {
+        return name;
+    }
+
+    let name: &'static str = Box::leak(name.into());
+    names.insert(name);
+    name
+}
+
+/// Formats a raw row with `decoder`, stripping the deadline of the values of expiring tables.
+pub(crate) fn format_with(
+    decoder: DynDecoder,
+    expiring: bool,
+    key: &[u8],
+    value: &[u8],
+) -> Result<String, DatabaseError> {
+    if expiring {
+        let (deadline, value) = expiry::split(value)?;
+        return Ok(format!("{} (expires at {deadline})", decoder(key, value)?));
+    }
+
+    decoder(key, value)
+}
+
+/// Formats bytes as a `0x` prefixed hex string.
+fn hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(2 + bytes.len() * 2);
+    out.push_str("0x");
+
+    bytes.iter().fold(out, |mut out, byte| {
+        let _ = write!(out, "{byte:02x}");
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use reth_db::{
+        table::{Compress, Encode},
+        transaction::{DbTx, DbTxMut},
+    };
+
+    use crate::{IntegerKey, test_utils};
+
+    db_table!((Plain) | u64, IntegerKey<u64>);
+
+    tables!(DynTables, 1, [Plain]);
+
+    #[test]
+    fn dyn_table_reads_typed_rows() {
+        let (_dir, db) = test_utils::provider::<DynTables>();
+        db.write(|tx| {
+            tx.put::<Plain>(1, IntegerKey(7))?;
+            tx.put::<Plain>(2, IntegerKey(8))
+        })
+        .unwrap()
+        .unwrap();
+
+        db.read(|tx| {
+            let table = tx.dyn_table("Plain").unwrap();
+            assert_eq!(table.name(), "Plain");
+            assert_eq!(
+                table.get(&1u64.encode()).unwrap(),
+                Some(IntegerKey(7u64).compress())
+            );
+            assert_eq!(table.get(&3u64.encode()).unwrap(), None);
+
+            let mut rows = Vec::new();
+            table
+                .for_each(|key, value| rows.push(table.format(key, value).unwrap()))
+                .unwrap();
+            assert_eq!(rows, ["1 => IntegerKey(7)", "2 => IntegerKey(8)"]);
+
+            assert!(tx.dyn_table("Missing").is_err());
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn dyn_table_writes_raw_rows() {
+        let (_dir, db) = test_utils::provider::<DynTables>();
+        db.write(|tx| {
+            let table = tx.dyn_table("Plain").unwrap();
+            table
+                .put(&1u64.encode(), &IntegerKey(7u64).compress())
+                .unwrap();
+            table
+                .put(&2u64.encode(), &IntegerKey(8u64).compress())
+                .unwrap();
+            assert!(table.delete(&2u64.encode(), None).unwrap());
+            assert!(!table.delete(&2u64.encode(), None).unwrap());
+
+            // Keys longer than a page can't be stored.
+            match table.put(&[0; 8192], &[]) {
+                Err(reth_db::DatabaseError::Write(err)) => {
+                    assert_eq!(err.table_name, "Plain");
+                    assert_eq!(err.key, [0; 8192]);
+                }
+                other => panic!("unexpected result {other:?}"),
+            }
+        })
+        .unwrap();
+
+        db.read(|tx| {
+            assert_eq!(tx.get::<Plain>(1).unwrap(), Some(IntegerKey(7)));
+            assert_eq!(tx.get::<Plain>(2).unwrap(), None);
+
+            let table = tx.dyn_table("Plain").unwrap();
+            let mut keys = Vec::new();
+            table.for_each(|key, _| keys.push(key.to_vec())).unwrap();
+            assert_eq!(keys, [1u64.encode()]);
+        })
+        .unwrap();
+    }
+}
@@ -1,3 +1,4 @@
 pub(crate) mod cursor;
+pub(crate) mod dyn_table;
 pub(crate) mod tx;
 pub(crate) mod utils;
@@ -1,26 +1,131 @@
-use std::{fmt::Debug, marker::PhantomData, str::FromStr, sync::Arc};
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
-use libmdbx_native::{DatabaseFlags, RO, RW, Transaction, TransactionKind, WriteFlags};
+use libmdbx_native::{
+    Canary, DatabaseFlags, Error, RO, RW, Transaction, TransactionKind, WriteFlags,
+};
 use parking_lot::RwLock;
 use reth_db::{
-    DatabaseError, DatabaseWriteOperation, TableType, Tables,
-    table::{Compress, DupSort, Encode, Key, Table, TableImporter},
+    DatabaseError, DatabaseWriteOperation, Tables,
+    cursor::DbDupCursorRO,
+    table::{Decompress, DupSort, Encode, Key, Table, TableImporter, TableRow},
     transaction::{DbTx, DbTxMut},
 };
 use reth_mdbx_sys::MDBX_dbi;
 use reth_storage_errors::db::DatabaseWriteError;
+use tracing::warn;
 
-use super::{cursor::LibmdbxCursor, utils::decode_one};
+use super::{
+    cursor::LibmdbxCursor,
+    dyn_table::DynTable,
+    utils::{compress_value, decode_one, encode_key, encode_row},
+};
+#[cfg(feature = "metrics")]
+use crate::metrics::TxMetrics;
 use crate::{
+    error::{self, ErrorContext, Operation},
+    expiry,
     // tables::{NUM_TABLES, Tables},
     implementation::DatabaseEnv,
-    traits::{TableDet, TableSet},
+    registry::{IndexEntry, TableRegistry},
+    trace::{self, Access},
+    traits::{SecondaryIndex, TableDet, TableSet},
 };
 
+/// Encodes the bounds of a key range for `operation`.
+pub(crate) fn encode_bounds<T: Table>(
+    operation: Operation,
+    range: &impl RangeBounds<T::Key>,
+) -> Result<(Bound<Vec<u8>>, Bound<Vec<u8>>), DatabaseError> {
+    let encode = |bound: Bound<&T::Key>| -> Result<Bound<Vec<u8>>, DatabaseError> {
+        Ok(match bound {
+            Bound::Included(key) => Bound::Included(encode_key::<T>(operation, key.clone())?),
+            Bound::Excluded(key) => Bound::Excluded(encode_key::<T>(operation, key.clone())?),
+            Bound::Unbounded => Bound::Unbounded,
+        })
+    };
+    Ok((encode(range.start_bound())?, encode(range.end_bound())?))
+}
+
+/// Borrows an encoded bound.
+pub(crate) fn as_slice_bound(bound: &Bound<Vec<u8>>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key.as_slice()),
+        Bound::Excluded(key) => Bound::Excluded(key.as_slice()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Number of leading key bytes bisected by [`LibmdbxTx::split_keys`].
+const SPLIT_PREFIX_LEN: usize = 16;
+
+/// Reads `key` as an integer ordered like the keys of the table: its first `len` bytes in
+/// big-endian order, or the whole native-endian key of `INTEGER_KEY` tables. Short keys are
+/// padded with zeros.
+fn key_prefix(key: &[u8], len: usize, integer_key: bool) -> u128 {
+    if integer_key {
+        return match <[u8; 4]>::try_from(key) {
+            Ok(key) => u32::from_ne_bytes(key).into(),
+            Err(_) => key.try_into().map_or(0, u64::from_ne_bytes).into(),
+        };
+    }
+
+    let mut buf = [0; SPLIT_PREFIX_LEN];
+    let n = key.len().min(len);
+    buf[..n].copy_from_slice(&key[..n]);
+    u128::from_be_bytes(buf) >> (8 * (SPLIT_PREFIX_LEN - len))
+}
+
+/// Inverse of [`key_prefix`].
+fn prefix_key(prefix: u128, len: usize, integer_key: bool) -> Vec<u8> {
+    match (integer_key, len) {
+        (true, 4) => (prefix as u32).to_ne_bytes().to_vec(),
+        (true, _) => (prefix as u64).to_ne_bytes().to_vec(),
+        (false, _) => (prefix << (8 * (SPLIT_PREFIX_LEN - len))).to_be_bytes()[..len].to_vec(),
+    }
+}
+
+/// Evaluates `$body`, timing it as `$operation` on `$table` when the transaction is measured.
+macro_rules! timed {
+    ($tx:expr, $table:expr, $operation:ident, $body:expr) => {{
+        #[cfg(feature = "metrics")]
+        let result = match &$tx.metrics {
+            Some(metrics) => metrics.time($table, crate::metrics::MetricOp::$operation, || $body),
+            None => $body,
+        };
+        #[cfg(not(feature = "metrics"))]
+        let result = $body;
+
+        result
+    }};
+}
+
+/// What [`LibmdbxTx::rewrite`] does with the row it is positioned on.
+enum Rewrite {
+    /// Leave the row untouched.
+    Keep,
+    /// Store the given compressed value.
+    Put(Vec<u8>),
+    /// Delete the row.
+    Delete,
+}
+
 #[derive(Debug)]
 pub struct LibmdbxTx<K: TransactionKind> {
     /// Libmdbx-sys transaction.
-    inner: Transaction<K>,
+    pub(crate) inner: Transaction<K>,
+    /// Per-table behaviour registered on the environment.
+    registry: Arc<TableRegistry>,
+    /// Measurements of the transaction, if the environment has a metrics sink.
+    #[cfg(feature = "metrics")]
+    metrics: Option<TxMetrics>,
     // /// Database table handle cache.
     // db_handles: Arc<RwLock<Vec<Option<DBI>>>>,
     // db_handles_len: usize,
@@ -30,45 +135,972 @@ impl LibmdbxTx<RO> {
     pub(crate) fn new_ro_tx(env: &DatabaseEnv) -> eyre::Result<LibmdbxTx<RO>, DatabaseError> {
         Ok(Self {
             inner: env
-                .begin_ro_txn()
-                .map_err(|e| DatabaseError::InitTx(e.into()))?,
+                .read_pool()
+                .checkout(env)
+                .map_err(|e| error::Error::Mdbx(e, ErrorContext::new(Operation::BeginTx)))?,
+            registry: env.table_registry().clone(),
+            #[cfg(feature = "metrics")]
+            metrics: env.tx_metrics(true),
             // db_handles: Arc::new(RwLock::new(vec![None; S::NUM_TABLES])),
             // db_handles_len: S::NUM_TABLES,
         })
     }
+
+    /// Ends the transaction like a commit, parking it in the read transaction pool of `env`, or
+    /// committing it if the pool is disabled or full.
+    pub(crate) fn release(self, env: &DatabaseEnv) -> Result<(), DatabaseError> {
+        match env.read_pool().release(self.inner) {
+            Some(inner) => Self { inner, ..self }.commit().map(|_| ()),
+            None => {
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = self.metrics {
+                    metrics.release();
+                }
+
+                Ok(())
+            }
+        }
+    }
 }
 
 impl LibmdbxTx<RW> {
+    /// Creates the table with its [`TableDet::database_flags`], failing if it is already stored
+    /// with other flags.
     pub fn create_table<T: TableDet>(&self, table: &T) -> Result<(), DatabaseError> {
-        let flags = match table.table_type() {
-            TableType::Table => DatabaseFlags::default(),
-            TableType::DupSort => DatabaseFlags::DUP_SORT,
+        let declared = table.database_flags();
+        // An existing table is opened with its stored flags, as creating it with other flags
+        // would make MDBX silently reflag it if it is empty.
+        let db = match self.inner.open_db(Some(T::NAME)) {
+            Err(Error::NotFound) => {
+                return self
+                    .inner
+                    .create_db(Some(T::NAME), declared)
+                    .map(|_| ())
+                    .map_err(|e| DatabaseError::CreateTable(e.into()));
+            }
+            db => db.map_err(|e| DatabaseError::CreateTable(e.into()))?,
         };
 
+        let stored = self
+            .inner
+            .db_flags(&db)
+            .map_err(|e| DatabaseError::CreateTable(e.into()))?;
+        if stored != declared {
+            return Err(DatabaseError::Other(format!(
+                "table {} is stored with flags {stored:?} but declared with {declared:?}",
+                T::NAME
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Creates the table tracking when the rows of expiring tables expire.
+    pub(crate) fn create_expiry_index(&self) -> Result<(), DatabaseError> {
         self.inner
-            .create_db(Some(T::NAME), flags)
+            .create_db(Some(expiry::EXPIRY_INDEX), DatabaseFlags::DUP_SORT)
             .map_err(|e| DatabaseError::CreateTable(e.into()))?;
 
         Ok(())
     }
 
+    /// Deletes the rows whose deadline has passed, handling at most `budget` expiry entries.
+    ///
+    /// Returns the number of expiry entries handled, including stale ones left behind by rows
+    /// that were rewritten or deleted in the meantime, and corrupt ones which are dropped with a
+    /// warning so that they don't fail every round.
+    pub(crate) fn reap_expired(&self, budget: usize) -> Result<usize, DatabaseError> {
+        let index = self
+            .inner
+            .open_db(Some(expiry::EXPIRY_INDEX))
+            .map_err(|e| DatabaseError::Open(e.into()))?;
+        let mut cursor = self
+            .inner
+            .cursor(&index)
+            .map_err(|e| DatabaseError::InitCursor(e.into()))?;
+
+        let now = expiry::now();
+        let mut processed = 0;
+
+        while processed < budget {
+            let Some((deadline, entry)) = cursor
+                .first::<Vec<u8>, Vec<u8>>()
+                .map_err(|e| DatabaseError::Read(e.into()))?
+            else {
+                break;
+            };
+
+            let parsed = expiry::split(&deadline)
+                .and_then(|(deadline, _)| Ok((deadline, expiry::parse_entry(&entry)?)));
+            match parsed {
+                Ok((deadline, _)) if deadline > now => break,
+                Ok((deadline, (table, key))) => self.reap_row(deadline, table, key)?,
+                Err(_) => warn!(
+                    target: "libmdbx_bindings",
+                    deadline = ?deadline,
+                    entry = ?entry,
+                    "Dropping corrupt expiry index entry"
+                ),
+            }
+
+            cursor
+                .del(WriteFlags::CURRENT)
+                .map_err(|e| DatabaseError::Delete(e.into()))?;
+            processed += 1;
+        }
+
+        Ok(processed)
+    }
+
+    /// Deletes the row `key` of `table` if it still expires at `deadline`.
+    fn reap_row(&self, deadline: u64, table: &str, key: &[u8]) -> Result<(), DatabaseError> {
+        // The table may have been dropped or renamed since the row was written.
+        let dbi = match self.inner.open_db(Some(table)) {
+            Ok(db) => db.dbi(),
+            Err(Error::NotFound) => return Ok(()),
+            Err(e) => return Err(DatabaseError::Open(e.into())),
+        };
+        let Some(stored) = self
+            .inner
+            .get::<Vec<u8>>(dbi, key)
+            .map_err(|e| DatabaseError::Read(e.into()))?
+        else {
+            return Ok(());
+        };
+
+        let Ok((stored_deadline, value)) = expiry::split(&stored) else {
+            warn!(
+                target: "libmdbx_bindings",
+                table,
+                key = ?key,
+                "Expiring row is too short to hold its deadline, keeping it"
+            );
+            return Ok(());
+        };
+        if stored_deadline == deadline {
+            self.inner
+                .del(dbi, key, None)
+                .map_err(|e| DatabaseError::Delete(e.into()))?;
+            self.maintain_indexes(self.registry.indexes_of(table), key, Some(value), None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes a row of an expiring table, comparing `data` against the value without its
+    /// deadline.
+    fn delete_expiring(
+        &self,
+        dbi: MDBX_dbi,
+        key: &[u8],
+        data: Option<&[u8]>,
+        indexes: &[IndexEntry],
+    ) -> Result<bool, DatabaseError> {
+        let Some(stored) = self
+            .inner
+            .get::<Vec<u8>>(dbi, key)
+            .map_err(|e| DatabaseError::Read(e.into()))?
+        else {
+            return Ok(false);
+        };
+
+        let (_, value) = expiry::split(&stored)?;
+        if data.is_some_and(|data| data != value) {
+            return Ok(false);
+        }
+
+        self.inner
+            .del(dbi, key, None)
+            .map_err(|e| DatabaseError::Delete(e.into()))?;
+        self.maintain_indexes(indexes, key, Some(value), None)?;
+
+        Ok(true)
+    }
+
+    /// Reads the value stored under `key`, passes it to `f` and stores the value `f` returns,
+    /// deleting the row if it returns `None`.
+    ///
+    /// The row is read and written through a single cursor position, so the key is only looked
+    /// up once. Only plain tables are supported.
+    pub fn update<T, F>(&self, key: T::Key, f: F) -> Result<(), DatabaseError>
+    where
+        T: Table,
+        F: FnOnce(Option<T::Value>) -> Option<T::Value>,
+    {
+        self.rewrite::<T>(key, |key, current| {
+            let current = current
+                .map(<T::Value as Decompress>::decompress)
+                .transpose()?;
+
+            Ok(match f(current) {
+                Some(value) => Rewrite::Put(compress_value::<T>(
+                    DatabaseWriteOperation::Put,
+                    key,
+                    &value,
+                )?),
+                None => Rewrite::Delete,
+            })
+        })
+        .map(|_| ())
+    }
+
+    /// Puts the `x`, `y` and `z` fields of the canary of the database, stored atomically with the
+    /// commit of the transaction.
+    ///
+    /// See [`LibmdbxProvider::checkpoint`](crate::LibmdbxProvider::checkpoint).
+    pub fn put_canary(&self, canary: Canary) -> Result<(), DatabaseError> {
+        self.inner
+            .put_canary(&canary)
+            .map_err(|e| error::Error::Mdbx(e, ErrorContext::new(Operation::Put)).into())
+    }
+
+    /// Increases the sequence of `T` by `increment` and returns its value before the increase, so
+    /// that calls with an increment of one hand out 0, 1, 2, and so on.
+    ///
+    /// The sequence is stored in the record of the table rather than in a row, and is rolled
+    /// back along with the transaction.
+    pub fn next_sequence<T: Table>(&self, increment: u64) -> Result<u64, DatabaseError> {
+        self.inner
+            .next_sequence(self.get_dbi::<T>()?, increment)
+            .map_err(|e| {
+                let context = ErrorContext::new(Operation::Put).with_table(T::NAME);
+                DatabaseError::from(error::Error::Mdbx(e, context))
+            })?
+            .ok_or_else(|| {
+                DatabaseError::Other(format!("sequence of table {} overflowed", T::NAME))
+            })
+    }
+
+    /// Replaces the value stored under `key` with `new` if it currently equals `expected`, where
+    /// `None` stands for a missing row on both sides.
+    ///
+    /// Returns `true` if the swap happened. Values are compared in their compressed form.
+    pub fn compare_and_swap<T: Table>(
+        &self,
+        key: T::Key,
+        expected: Option<T::Value>,
+        new: Option<T::Value>,
+    ) -> Result<bool, DatabaseError> {
+        self.rewrite::<T>(key, |key, current| {
+            let expected = expected
+                .map(|value| compress_value::<T>(DatabaseWriteOperation::Put, key, &value))
+                .transpose()?;
+            if current != expected.as_deref() {
+                return Ok(Rewrite::Keep);
+            }
+
+            Ok(match new {
+                Some(value) => Rewrite::Put(compress_value::<T>(
+                    DatabaseWriteOperation::Put,
+                    key,
+                    &value,
+                )?),
+                None => Rewrite::Delete,
+            })
+        })
+    }
+
+    /// Positions a cursor on `key` and applies the [`Rewrite`] returned by `f` for the encoded key
+    /// and the compressed live value, keeping the expiry and secondary indexes in sync.
+    ///
+    /// Returns `false` if `f` returned [`Rewrite::Keep`].
+    fn rewrite<T: Table>(
+        &self,
+        key: T::Key,
+        f: impl FnOnce(&[u8], Option<&[u8]>) -> Result<Rewrite, DatabaseError>,
+    ) -> Result<bool, DatabaseError> {
+        if T::DUPSORT {
+            return Err(DatabaseError::Other(format!(
+                "cannot rewrite rows of the DUPSORT table {}",
+                T::NAME
+            )));
+        }
+
+        let key = encode_key::<T>(DatabaseWriteOperation::Put, key)?;
+        let mut cursor = self
+            .inner
+            .cursor_with_dbi(self.get_dbi::<T>()?)
+            .map_err(|e| {
+                let context = ErrorContext::new(Operation::Cursor).with_table(T::NAME);
+                DatabaseError::from(error::Error::Mdbx(e, context))
+            })?;
+
+        let stored = cursor
+            .set_key::<(), Vec<u8>>(&key)
+            .map_err(|e| DatabaseError::Read(e.into()))?
+            .map(|(_, value)| value);
+
+        // The raw value is what the indexes were built from, even if the row already expired.
+        let ttl = self.registry.ttl_of(T::NAME);
+        let (old, live) = match (&stored, ttl) {
+            (Some(stored), Some(_)) => {
+                let (deadline, value) = expiry::split(stored)?;
+                (
+                    Some(value),
+                    (!expiry::is_expired(deadline)).then_some(value),
+                )
+            }
+            (stored, _) => (stored.as_deref(), stored.as_deref()),
+        };
+
+        let new = match f(&key, live)? {
+            Rewrite::Keep => return Ok(false),
+            Rewrite::Delete if stored.is_none() => return Ok(true),
+            Rewrite::Delete => {
+                cursor
+                    .del(WriteFlags::CURRENT)
+                    .map_err(|e| DatabaseError::Delete(e.into()))?;
+                None
+            }
+            Rewrite::Put(value) => {
+                let deadline = ttl.map(expiry::deadline);
+                let data = match deadline {
+                    Some(deadline) => Cow::Owned(expiry::stamp(deadline, &value)),
+                    None => Cow::Borrowed(value.as_slice()),
+                };
+                let flags = if stored.is_some() {
+                    WriteFlags::CURRENT
+                } else {
+                    WriteFlags::UPSERT
+                };
+
+                cursor.put(&key, &data, flags).map_err(|e| {
+                    DatabaseError::from(DatabaseWriteError {
+                        info: e.into(),
+                        operation: DatabaseWriteOperation::CursorUpsert,
+                        table_name: T::NAME,
+                        key: key.clone(),
+                    })
+                })?;
+
+                if let Some(deadline) = deadline {
+                    expiry::track(&self.inner, T::NAME, deadline, &key)?;
+                }
+
+                Some(value)
+            }
+        };
+
+        self.maintain_indexes(self.registry.indexes_of(T::NAME), &key, old, new.as_deref())?;
+
+        Ok(true)
+    }
+
+    /// Deletes every entry whose key falls in `range` and returns the number of deleted
+    /// entries, counting every duplicate of a DUPSORT table.
+    ///
+    /// Use [`LibmdbxProvider::delete_range`](crate::LibmdbxProvider::delete_range) to split huge
+    /// ranges over several transactions.
+    pub fn delete_range<T: Table>(
+        &self,
+        range: impl RangeBounds<T::Key>,
+    ) -> Result<usize, DatabaseError> {
+        let (start, end) = encode_bounds::<T>(Operation::Delete, &range)?;
+        self.delete_encoded_range::<T>(as_slice_bound(&start), as_slice_bound(&end), usize::MAX)
+    }
+
+    /// Deletes at most `limit` entries whose encoded key falls between `start` and `end`.
+    pub(crate) fn delete_encoded_range<T: Table>(
+        &self,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        limit: usize,
+    ) -> Result<usize, DatabaseError> {
+        let dbi = self.get_dbi::<T>()?;
+        let mut cursor = self.inner.cursor_with_dbi(dbi).map_err(|e| {
+            let context = ErrorContext::new(Operation::Cursor).with_table(T::NAME);
+            DatabaseError::from(error::Error::Mdbx(e, context))
+        })?;
+
+        // Keys are compared in the order of the table, which isn't the order of their bytes for
+        // `INTEGER_KEY` tables.
+        let compare = |a: &[u8], b: &[u8]| {
+            self.inner
+                .compare_keys(dbi, a, b)
+                .map_err(|e| DatabaseError::Read(e.into()))
+        };
+        let indexes = self.registry.indexes_of(T::NAME);
+        let ttl = self.registry.ttl_of(T::NAME);
+
+        let mut row = match start {
+            Bound::Included(key) | Bound::Excluded(key) => {
+                cursor.set_range::<Vec<u8>, Vec<u8>>(key)
+            }
+            Bound::Unbounded => cursor.first::<Vec<u8>, Vec<u8>>(),
+        }
+        .map_err(|e| DatabaseError::Read(e.into()))?;
+
+        let mut deleted = 0;
+        while deleted < limit {
+            let Some((key, value)) = row else {
+                break;
+            };
+
+            let at_start = match start {
+                Bound::Excluded(start) => compare(&key, start)?.is_eq(),
+                _ => false,
+            };
+            if at_start {
+                row = cursor.next().map_err(|e| DatabaseError::Read(e.into()))?;
+                continue;
+            }
+
+            let past_end = match end {
+                Bound::Included(end) => compare(&key, end)?.is_gt(),
+                Bound::Excluded(end) => compare(&key, end)?.is_ge(),
+                Bound::Unbounded => false,
+            };
+            if past_end {
+                break;
+            }
+
+            cursor
+                .del(WriteFlags::CURRENT)
+                .map_err(|e| DatabaseError::Delete(e.into()))?;
+
+            if !indexes.is_empty() {
+                let old = match ttl {
+                    Some(_) => expiry::split(&value)?.1,
+                    None => &value,
+                };
+                self.maintain_indexes(indexes, &key, Some(old), None)?;
+            }
+
+            deleted += 1;
+            row = cursor.next().map_err(|e| DatabaseError::Read(e.into()))?;
+        }
+
+        Ok(deleted)
+    }
+
+    /// Deletes `T` and its content from the database, along with the content of its secondary
+    /// indexes.
+    ///
+    /// # Safety
+    ///
+    /// Every cursor opened on `T` by this transaction must be dropped beforehand, MDBX closes the
+    /// handle they use.
+    pub unsafe fn drop_table<T: Table>(&self) -> Result<(), DatabaseError> {
+        // SAFETY: forwarded to the caller.
+        unsafe { self.drop_table_by_name(T::NAME) }?;
+
+        for index in self.registry.indexes_of(T::NAME) {
+            let db = self
+                .inner
+                .open_db(Some(index.table))
+                .map_err(|e| DatabaseError::Open(e.into()))?;
+            self.inner
+                .clear_db(db.dbi())
+                .map_err(|e| DatabaseError::Delete(e.into()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the table `name` and its content from the database.
+    ///
+    /// Meant for tables that are no longer part of the [`TableSet`], so secondary indexes are not
+    /// touched.
+    ///
+    /// # Safety
+    ///
+    /// Every cursor opened on the table by this transaction must be dropped beforehand, MDBX
+    /// closes the handle they use.
+    pub unsafe fn drop_table_by_name(&self, name: &str) -> Result<(), DatabaseError> {
+        let db = self
+            .inner
+            .open_db(Some(name))
+            .map_err(|e| DatabaseError::Open(e.into()))?;
+
+        // SAFETY: the handle is only shared with the cursors of this transaction, which the
+        // caller guarantees were dropped.
+        unsafe { self.inner.drop_db(db) }.map_err(|e| DatabaseError::Delete(e.into()))
+    }
+
+    /// Renames the table `old` to `new`, which must not exist yet. `new` is usually the
+    /// [`Table::NAME`] of the table the rows are migrated to.
+    ///
+    /// libmdbx 0.12 has no `mdbx_dbi_rename`, so the entries are copied into a new table created
+    /// with the same flags before `old` is dropped. Registered secondary indexes and expiry
+    /// entries still refer to `old`, so this is meant for migrating tables that have neither.
+    ///
+    /// # Safety
+    ///
+    /// Every cursor opened on `old` by this transaction must be dropped beforehand, like for
+    /// [`LibmdbxTx::drop_table_by_name`].
+    pub unsafe fn rename_table(&self, old: &str, new: &'static str) -> Result<(), DatabaseError> {
+        match self.inner.open_db(Some(new)) {
+            Ok(_) => {
+                return Err(DatabaseError::CreateTable(
+                    Error::KeyExist.to_err_code().into(),
+                ));
+            }
+            Err(Error::NotFound) => (),
+            Err(e) => return Err(DatabaseError::Open(e.into())),
+        }
+
+        let source = self
+            .inner
+            .open_db(Some(old))
+            .map_err(|e| DatabaseError::Open(e.into()))?;
+        let flags = self
+            .inner
+            .db_flags(&source)
+            .map_err(|e| DatabaseError::Open(e.into()))?;
+        let target = self
+            .inner
+            .create_db(Some(new), flags)
+            .map_err(|e| DatabaseError::CreateTable(e.into()))?;
+
+        {
+            let mut cursor = self
+                .inner
+                .cursor(&source)
+                .map_err(|e| DatabaseError::InitCursor(e.into()))?;
+            let mut row = cursor
+                .first::<Vec<u8>, Vec<u8>>()
+                .map_err(|e| DatabaseError::Read(e.into()))?;
+
+            while let Some((key, value)) = row {
+                self.inner
+                    .put(target.dbi(), &key, &value, WriteFlags::UPSERT)
+                    .map_err(|e| {
+                        DatabaseError::from(DatabaseWriteError {
+                            info: e.into(),
+                            operation: DatabaseWriteOperation::Put,
+                            table_name: new,
+                            key: key.clone(),
+                        })
+                    })?;
+                row = cursor.next().map_err(|e| DatabaseError::Read(e.into()))?;
+            }
+        }
+
+        // SAFETY: the cursor copying `old` was dropped above, the caller guarantees the others
+        // were.
+        unsafe { self.inner.drop_db(source) }.map_err(|e| DatabaseError::Delete(e.into()))
+    }
+
     pub(crate) fn new_rw_tx(env: &DatabaseEnv) -> Result<LibmdbxTx<RW>, DatabaseError> {
+        Self::from_rw_txn(env, env.begin_rw_txn())
+    }
+
+    /// Begins a read-write transaction, failing with [`Error::Busy`] instead of waiting for the
+    /// write lock.
+    pub(crate) fn try_new_rw_tx(env: &DatabaseEnv) -> Result<LibmdbxTx<RW>, DatabaseError> {
+        Self::from_rw_txn(env, env.try_begin_rw_txn())
+    }
+
+    /// Begins a read-write transaction, waiting at most `timeout` for the write lock.
+    pub(crate) fn new_rw_tx_timeout(
+        env: &DatabaseEnv,
+        timeout: Duration,
+    ) -> Result<LibmdbxTx<RW>, DatabaseError> {
+        Self::from_rw_txn(env, env.begin_rw_txn_timeout(timeout))
+    }
+
+    fn from_rw_txn(
+        env: &DatabaseEnv,
+        txn: Result<Transaction<RW>, Error>,
+    ) -> Result<LibmdbxTx<RW>, DatabaseError> {
         Ok(Self {
-            inner: env
-                .begin_rw_txn()
-                .map_err(|e| DatabaseError::InitTx(e.into()))?,
+            inner: txn.map_err(|e| error::Error::Mdbx(e, ErrorContext::new(Operation::BeginTx)))?,
+            registry: env.table_registry().clone(),
+            #[cfg(feature = "metrics")]
+            metrics: env.tx_metrics(false),
             // db_handles: Arc::new(RwLock::new(vec![None; S::NUM_TABLES])),
         })
     }
+
+    /// Stores a row of `T`, stamping expiring values and maintaining the secondary indexes.
+    fn put_row<T: Table>(&self, key: T::Key, value: T::Value) -> Result<(), DatabaseError> {
+        let (key, value) = encode_row::<T>(DatabaseWriteOperation::Put, key, &value)?;
+        let dbi = self.get_dbi::<T>()?;
+        let ttl = self.registry.ttl_of(T::NAME);
+
+        // Putting into a DUPSORT table adds a duplicate, so only plain tables replace a row.
+        let indexes = self.registry.indexes_of(T::NAME);
+        let old = if indexes.is_empty() || T::DUPSORT {
+            None
+        } else {
+            self.inner
+                .get::<Vec<u8>>(dbi, &key)
+                .map_err(|e| DatabaseError::Read(e.into()))?
+        };
+
+        let deadline = ttl.map(expiry::deadline);
+        let stored = match deadline {
+            Some(deadline) => Cow::Owned(expiry::stamp(deadline, value.as_slice())),
+            None => Cow::Borrowed(value.as_slice()),
+        };
+        trace::record_sizes(key.len(), Some(stored.len()));
+
+        self.inner
+            .put(dbi, &key, &stored, WriteFlags::UPSERT)
+            .map_err(|e| error::Error::Mdbx(e, Self::context::<T>(Operation::Put, &key)))?;
+
+        if let Some(deadline) = deadline {
+            expiry::track(&self.inner, T::NAME, deadline, &key)?;
+        }
+
+        let old = match (&old, ttl) {
+            (Some(old), Some(_)) => Some(expiry::split(old)?.1),
+            (old, _) => old.as_deref(),
+        };
+        self.maintain_indexes(indexes, &key, old, Some(value.as_slice()))
+    }
+
+    /// Deletes a row of `T`, maintaining the secondary indexes and the expiry index.
+    fn delete_row<T: Table>(
+        &self,
+        key: T::Key,
+        value: Option<T::Value>,
+    ) -> Result<bool, DatabaseError> {
+        let key = encode_key::<T>(Operation::Delete, key)?;
+        let value = value
+            .map(|value| compress_value::<T>(Operation::Delete, &key, &value))
+            .transpose()?;
+        let data = value.as_deref();
+
+        let dbi = self.get_dbi::<T>()?;
+        trace::record_sizes(key.as_ref().len(), data.map(<[u8]>::len));
+
+        let indexes = self.registry.indexes_of(T::NAME);
+        if self.registry.ttl_of(T::NAME).is_some() {
+            return self.delete_expiring(dbi, key.as_ref(), data, indexes);
+        }
+
+        let old = if indexes.is_empty() {
+            Vec::new()
+        } else {
+            self.values_to_delete::<T>(dbi, key.as_ref(), data)?
+        };
+
+        let deleted = self.inner.del(dbi, key.as_ref(), data).map_err(|e| {
+            error::Error::Mdbx(e, Self::context::<T>(Operation::Delete, key.as_ref()))
+        })?;
+
+        if deleted {
+            for old in &old {
+                self.maintain_indexes(indexes, key.as_ref(), Some(old), None)?;
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Returns every value stored under `key` that a delete with the given `data` would remove.
+    fn values_to_delete<T: Table>(
+        &self,
+        dbi: MDBX_dbi,
+        key: &[u8],
+        data: Option<&[u8]>,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        if let Some(data) = data {
+            return Ok(vec![data.to_vec()]);
+        }
+
+        if !T::DUPSORT {
+            return Ok(self
+                .inner
+                .get::<Vec<u8>>(dbi, key)
+                .map_err(|e| DatabaseError::Read(e.into()))?
+                .into_iter()
+                .collect());
+        }
+
+        let mut cursor = self
+            .inner
+            .cursor_with_dbi(dbi)
+            .map_err(|e| DatabaseError::InitCursor(e.into()))?;
+        cursor
+            .iter_dup_of::<(), Vec<u8>>(key)
+            .map(|entry| {
+                entry
+                    .map(|(_, value)| value)
+                    .map_err(|e| DatabaseError::Read(e.into()))
+            })
+            .collect()
+    }
+
+    /// Runs the secondary index hooks after a row of a primary table changed.
+    fn maintain_indexes(
+        &self,
+        indexes: &[IndexEntry],
+        key: &[u8],
+        old: Option<&[u8]>,
+        new: Option<&[u8]>,
+    ) -> Result<(), DatabaseError> {
+        for index in indexes {
+            (index.maintain)(self, key, old, new)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl<K: TransactionKind> LibmdbxTx<K> {
+    /// Looks up the rows of `I::Primary` whose extracted index key is `index_key`.
+    pub fn get_by_index<I>(
+        &self,
+        index_key: I::Key,
+    ) -> Result<Vec<TableRow<I::Primary>>, DatabaseError>
+    where
+        I: SecondaryIndex,
+        I::Value: Into<<I::Primary as Table>::Key>,
+    {
+        let mut cursor = self.new_cursor::<I>()?;
+        let mut rows = Vec::new();
+
+        for entry in cursor.walk_dup(Some(index_key.clone()), None)? {
+            let (_, primary_key) = entry?;
+            let primary_key: <I::Primary as Table>::Key = primary_key.into();
+
+            if <I::Primary as Table>::DUPSORT {
+                rows.extend(self.indexed_dups::<I>(&primary_key, &index_key)?);
+            } else if let Some(value) = self.get::<I::Primary>(primary_key.clone())? {
+                rows.push((primary_key, value));
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Returns the duplicates stored under `primary_key` in the DUPSORT primary table of `I`
+    /// whose extracted index key is `index_key`.
+    pub(crate) fn indexed_dups<I: SecondaryIndex>(
+        &self,
+        primary_key: &<I::Primary as Table>::Key,
+        index_key: &I::Key,
+    ) -> Result<Vec<TableRow<I::Primary>>, DatabaseError> {
+        let key = encode_key::<I::Primary>(Operation::Get, primary_key.clone())?;
+        let mut cursor = self
+            .inner
+            .cursor_with_dbi(self.get_dbi::<I::Primary>()?)
+            .map_err(|e| DatabaseError::InitCursor(e.into()))?;
+
+        let mut rows = Vec::new();
+        for entry in cursor.iter_dup_of::<(), Vec<u8>>(&key) {
+            let (_, value) = entry.map_err(|e| DatabaseError::Read(e.into()))?;
+            let value = <<I::Primary as Table>::Value as Decompress>::decompress(&value)?;
+
+            if I::extract(primary_key, &value).as_ref() == Some(index_key) {
+                rows.push((primary_key.clone(), value));
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Returns an untyped handle to the table `name`.
+    pub fn dyn_table(&self, name: &str) -> Result<DynTable<'_, K>, DatabaseError> {
+        DynTable::new(self, name)
+    }
+
+    /// Per-table behaviour registered on the environment.
+    pub(crate) fn registry(&self) -> &TableRegistry {
+        &self.registry
+    }
+
+    /// Estimates the number of rows of `T` whose key falls in `range` with
+    /// `mdbx_estimate_range`.
+    ///
+    /// The estimate is exact for small tables and degrades gracefully with their size. It counts
+    /// every duplicate of DUPSORT tables, as well as the expired rows not reaped yet.
+    /// `mdbx_estimate_range` counts from an included start up to an excluded end, so the rows
+    /// stored under an excluded start or an included end are read to adjust the estimate.
+    pub fn estimate_range<T: Table>(
+        &self,
+        range: impl RangeBounds<T::Key>,
+    ) -> Result<usize, DatabaseError> {
+        let (start, end) = encode_bounds::<T>(Operation::Stats, &range)?;
+        let key = |bound: &Bound<Vec<u8>>| match bound {
+            Bound::Included(key) | Bound::Excluded(key) => Some(key.as_slice()),
+            Bound::Unbounded => None,
+        };
+
+        let dbi = self.get_dbi::<T>()?;
+        let mut distance = self
+            .inner
+            .estimate_range(dbi, key(&start), key(&end))
+            .map_err(|e| DatabaseError::Read(e.into()))?;
+        if let Bound::Excluded(key) = &start {
+            distance -= self.count_stored::<T>(dbi, key)? as isize;
+        }
+        if let Bound::Included(key) = &end {
+            distance += self.count_stored::<T>(dbi, key)? as isize;
+        }
+
+        Ok(distance.max(0) as usize)
+    }
+
+    /// Counts the values of `T` stored under the encoded `key`, every duplicate of DUPSORT tables
+    /// included.
+    fn count_stored<T: Table>(&self, dbi: MDBX_dbi, key: &[u8]) -> Result<usize, DatabaseError> {
+        if !T::DUPSORT {
+            return Ok(self
+                .inner
+                .get::<()>(dbi, key)
+                .map_err(|e| DatabaseError::Read(e.into()))?
+                .map_or(0, |()| 1));
+        }
+
+        let mut cursor = self
+            .inner
+            .cursor_with_dbi(dbi)
+            .map_err(|e| DatabaseError::InitCursor(e.into()))?;
+        cursor
+            .iter_dup_of::<(), ()>(key)
+            .try_fold(0, |count, entry| {
+                entry
+                    .map(|_| count + 1)
+                    .map_err(|e| DatabaseError::Read(e.into()))
+            })
+    }
+
+    /// Reads the canary of the database as of this transaction.
+    pub fn canary(&self) -> Result<Canary, DatabaseError> {
+        self.inner
+            .canary()
+            .map_err(|e| error::Error::Mdbx(e, ErrorContext::new(Operation::Get)).into())
+    }
+
+    /// Returns the current value of the sequence of `T`, see [`LibmdbxTx::next_sequence`].
+    pub fn current_sequence<T: Table>(&self) -> Result<u64, DatabaseError> {
+        self.inner
+            .current_sequence(self.get_dbi::<T>()?)
+            .map_err(|e| {
+                let context = ErrorContext::new(Operation::Get).with_table(T::NAME);
+                error::Error::Mdbx(e, context).into()
+            })
+    }
+
+    /// Splits the keys of `T` into at most `partitions` ranges holding roughly the same number of
+    /// rows, returning the stored key starting every range but the first.
+    ///
+    /// The boundaries are found by bisecting the leading bytes of the keys, or the whole integer
+    /// key of `INTEGER_KEY` tables, with `mdbx_estimate_range`, so no row is read.
+    pub(crate) fn split_keys<T: Table>(
+        &self,
+        partitions: usize,
+    ) -> Result<Vec<Vec<u8>>, DatabaseError> {
+        let dbi = self.get_dbi::<T>()?;
+        let read = |e: Error| DatabaseError::Read(e.into());
+        let estimate = |end: Option<&[u8]>| self.inner.estimate_range(dbi, None, end).map_err(read);
+        let integer_key = self
+            .inner
+            .db_flags_with_dbi(dbi)
+            .map_err(read)?
+            .contains(DatabaseFlags::INTEGER_KEY);
+
+        let total = estimate(None)?.max(0) as usize;
+        if partitions < 2 || total < partitions {
+            return Ok(Vec::new());
+        }
+
+        let mut cursor = self.inner.cursor_with_dbi(dbi).map_err(|e| {
+            let context = ErrorContext::new(Operation::Cursor).with_table(T::NAME);
+            DatabaseError::from(error::Error::Mdbx(e, context))
+        })?;
+        let first = cursor.first::<Vec<u8>, ()>().map_err(read)?;
+        let last = cursor.last::<Vec<u8>, ()>().map_err(read)?;
+        let (Some((first, _)), Some((last, _))) = (first, last) else {
+            return Ok(Vec::new());
+        };
+
+        let len = first.len().max(last.len()).clamp(1, SPLIT_PREFIX_LEN);
+        let (mut low, high) = (
+            key_prefix(&first, len, integer_key),
+            key_prefix(&last, len, integer_key),
+        );
+        let mut boundaries: Vec<Vec<u8>> = Vec::with_capacity(partitions - 1);
+        for partition in 1..partitions {
+            // Smallest prefix with at least `target` rows before it.
+            let target = (total * partition / partitions) as isize;
+            let mut high = high;
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if estimate(Some(&prefix_key(mid, len, integer_key)))? < target {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+
+            let Some((key, _)) = cursor
+                .set_range::<Vec<u8>, ()>(&prefix_key(low, len, integer_key))
+                .map_err(read)?
+            else {
+                break;
+            };
+            if key != first && boundaries.last() != Some(&key) {
+                boundaries.push(key);
+            }
+        }
+
+        Ok(boundaries)
+    }
+
+    /// Reads the row of `T` stored under the encoded `key` like [`DbTx::get`].
+    fn get_traced<T: Table>(&self, key: &[u8]) -> Result<Option<T::Value>, DatabaseError> {
+        let get = || self.get_row::<T>(key);
+        timed!(
+            self,
+            T::NAME,
+            Get,
+            trace::traced(T::NAME, "get", Access::Read, get)
+        )
+    }
+
+    /// Reads the value of `T` stored under the encoded `key`, hiding expired rows.
+    fn get_row<T: Table>(&self, key: &[u8]) -> Result<Option<T::Value>, DatabaseError> {
+        let value = self
+            .inner
+            .get(self.get_dbi::<T>()?, key)
+            .map_err(|e| error::Error::Mdbx(e, Self::context::<T>(Operation::Get, key)))?;
+        trace::record_sizes(
+            key.len(),
+            value.as_ref().map(|value: &Cow<'_, [u8]>| value.len()),
+        );
+
+        let Some(value) = value else {
+            return Ok(None);
+        };
+
+        let codec_error = |e: DatabaseError| {
+            DatabaseError::from(error::Error::Codec(
+                e.into(),
+                Self::context::<T>(Operation::Get, key),
+            ))
+        };
+        if self.registry.ttl_of(T::NAME).is_some() {
+            let (deadline, value) = expiry::strip(value).map_err(codec_error)?;
+            if expiry::is_expired(deadline) {
+                return Ok(None);
+            }
+
+            return decode_one::<T>(value).map(Some).map_err(codec_error);
+        }
+
+        decode_one::<T>(value).map(Some).map_err(codec_error)
+    }
+
+    /// Context of an error of `operation` on the row of `T` stored under the encoded `key`.
+    fn context<T: Table>(operation: Operation, key: &[u8]) -> ErrorContext {
+        ErrorContext::new(operation)
+            .with_table(T::NAME)
+            .with_key(key)
+    }
+
     /// Gets a table database handle if it exists, otherwise creates it.
     pub(crate) fn get_dbi<T: Table>(&self) -> Result<MDBX_dbi, DatabaseError> {
         self.inner
             .open_db(Some(T::NAME))
             .map(|db| db.dbi())
-            .map_err(|e| DatabaseError::Open(e.into()))
+            .map_err(|e| {
+                let context = ErrorContext::new(Operation::Open).with_table(T::NAME);
+                error::Error::Mdbx(e, context).into()
+            })
     }
 
     /// Create db Cursor
@@ -76,9 +1108,12 @@ impl<K: TransactionKind> LibmdbxTx<K> {
         let inner = self
             .inner
             .cursor_with_dbi(self.get_dbi::<T>()?)
-            .map_err(|e| DatabaseError::InitCursor(e.into()))?;
+            .map_err(|e| {
+                let context = ErrorContext::new(Operation::Cursor).with_table(T::NAME);
+                DatabaseError::from(error::Error::Mdbx(e, context))
+            })?;
 
-        Ok(LibmdbxCursor::new(inner))
+        Ok(LibmdbxCursor::new(inner, self.registry.ttl_of(T::NAME)))
     }
 }
 
@@ -87,25 +1122,32 @@ impl<K: TransactionKind> DbTx for LibmdbxTx<K> {
     type DupCursor<T: DupSort> = LibmdbxCursor<T, K>;
 
     fn get<T: Table>(&self, key: T::Key) -> Result<Option<<T as Table>::Value>, DatabaseError> {
-        self.get_by_encoded_key::<T>(&key.encode())
+        let key = encode_key::<T>(Operation::Get, key)?;
+        self.get_traced::<T>(&key)
     }
 
     fn get_by_encoded_key<T: Table>(
         &self,
         key: &<T::Key as Encode>::Encoded,
     ) -> Result<Option<T::Value>, DatabaseError> {
-        self.inner
-            .get(self.get_dbi::<T>()?, key.as_ref())
-            .map_err(|e| DatabaseError::Read(e.into()))?
-            .map(decode_one::<T>)
-            .transpose()
+        self.get_traced::<T>(key.as_ref())
     }
 
     fn commit(self) -> Result<bool, DatabaseError> {
-        self.inner
+        #[cfg(feature = "metrics")]
+        let (metrics, env) = (self.metrics, self.inner.env().clone());
+
+        let (res, _latency) = self
+            .inner
             .commit()
-            .map(|(res, _latency)| res)
-            .map_err(|e| DatabaseError::Commit(e.into()))
+            .map_err(|e| error::Error::Mdbx(e, ErrorContext::new(Operation::Commit)))?;
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = metrics {
+            metrics.commit(&_latency, &env);
+        }
+
+        Ok(res)
     }
 
     fn disable_long_read_transaction_safety(&mut self) {
@@ -141,24 +1183,13 @@ impl DbTxMut for LibmdbxTx<RW> {
     type DupCursorMut<T: DupSort> = LibmdbxCursor<T, RW>;
 
     fn put<T: Table>(&self, key: T::Key, value: T::Value) -> Result<(), DatabaseError> {
-        let key = key.encode();
-        let value = value.compress();
-        self.inner
-            .put(
-                self.get_dbi::<T>()?,
-                key.as_ref(),
-                value,
-                WriteFlags::UPSERT,
-            )
-            .map_err(|e| {
-                DatabaseWriteError {
-                    info: e.into(),
-                    operation: DatabaseWriteOperation::Put,
-                    table_name: T::NAME,
-                    key: key.into(),
-                }
-                .into()
-            })
+        let put = || self.put_row::<T>(key, value);
+        timed!(
+            self,
+            T::NAME,
+            Put,
+            trace::traced(T::NAME, "put", Access::Write, put)
+        )
     }
 
     fn delete<T: Table>(
@@ -166,24 +1197,33 @@ impl DbTxMut for LibmdbxTx<RW> {
         key: T::Key,
         value: Option<T::Value>,
     ) -> Result<bool, DatabaseError> {
-        let mut data = None;
-
-        let value = value.map(Compress::compress);
-        if let Some(value) = &value {
-            data = Some(value.as_ref());
-        };
-
-        self.inner
-            .del(self.get_dbi::<T>()?, key.encode(), data)
-            .map_err(|e| DatabaseError::Delete(e.into()))
+        let delete = || self.delete_row::<T>(key, value);
+        timed!(
+            self,
+            T::NAME,
+            Delete,
+            trace::traced(T::NAME, "delete", Access::Write, delete)
+        )
     }
 
     fn clear<T: Table>(&self) -> Result<(), DatabaseError> {
-        self.inner
-            .clear_db(self.get_dbi::<T>()?)
-            .map_err(|e| DatabaseError::Delete(e.into()))?;
+        trace::traced(T::NAME, "clear", Access::Write, || {
+            self.inner
+                .clear_db(self.get_dbi::<T>()?)
+                .map_err(|e| DatabaseError::Delete(e.into()))?;
 
-        Ok(())
+            for index in self.registry.indexes_of(T::NAME) {
+                let db = self
+                    .inner
+                    .open_db(Some(index.table))
+                    .map_err(|e| DatabaseError::Open(e.into()))?;
+                self.inner
+                    .clear_db(db.dbi())
+                    .map_err(|e| DatabaseError::Delete(e.into()))?;
+            }
+
+            Ok(())
+        })
     }
 
     fn cursor_write<T: Table>(&self) -> Result<Self::CursorMut<T>, DatabaseError> {
@@ -196,3 +1236,370 @@ impl DbTxMut for LibmdbxTx<RW> {
 }
 
 impl TableImporter for LibmdbxTx<RW> {}
+
+#[cfg(test)]
+mod tests {
+    use reth_db::{cursor::DbCursorRO, transaction::DbTxMut};
+
+    use super::*;
+    use crate::{IntegerKey, test_utils};
+
+    db_table!((Plain) | u64, IntegerKey<u64>);
+    db_table!((Small, integer_key) | u32, IntegerKey<u64>);
+    db_table!((Large, integer_key) | u64, IntegerKey<u64>);
+    db_table!((Dups, integer_key, integer_dup) | u64 => u64);
+
+    tables!(TxTables, 4, [Plain, Small, Large, Dups]);
+
+    // Target of the rename of `Dups`, outside of the table set.
+    db_table!((Renamed, integer_key, integer_dup) | u64 => u64);
+
+    /// `Plain` declared with other flags.
+    mod redeclared {
+        use crate::IntegerKey;
+
+        db_table!((Plain, integer_key) | u64, IntegerKey<u64>);
+    }
+
+    #[test]
+    fn create_table_rejects_other_flags() {
+        let (_dir, db) = test_utils::provider::<TxTables>();
+        let redeclare = || {
+            let err = db
+                .write(|tx| tx.create_table(&redeclared::Plain))
+                .unwrap()
+                .unwrap_err();
+            assert!(
+                err.to_string().contains("table Plain is stored with flags"),
+                "{err}"
+            );
+        };
+
+        // An empty table isn't reflagged.
+        redeclare();
+        db.write(|tx| tx.put::<Plain>(1, IntegerKey(1)))
+            .unwrap()
+            .unwrap();
+        redeclare();
+
+        db.write(|tx| tx.create_table(&Plain)).unwrap().unwrap();
+        db.read(|tx| {
+            let plain = tx.inner.open_db(Some("Plain")).unwrap();
+            assert_eq!(tx.inner.db_flags(&plain).unwrap(), DatabaseFlags::default());
+            assert_eq!(tx.get::<Plain>(1).unwrap(), Some(IntegerKey(1)));
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn integer_keys_round_trip() {
+        let (_dir, db) = test_utils::provider::<TxTables>();
+        let keys = [65_536, 0, u32::MAX, 256, 1, 255];
+
+        db.write(|tx| {
+            for key in keys {
+                tx.put::<Small>(IntegerKey(key), IntegerKey(key.into()))?;
+                tx.put::<Large>(IntegerKey(key.into()), IntegerKey(key.into()))?;
+            }
+            tx.put::<Large>(IntegerKey(u64::MAX), IntegerKey(u64::MAX))
+        })
+        .unwrap()
+        .unwrap();
+
+        let mut sorted = keys.map(u64::from).to_vec();
+        sorted.sort();
+        let (small, large) = db
+            .read(|tx| {
+                assert_eq!(
+                    tx.get::<Small>(IntegerKey(256)).unwrap(),
+                    Some(IntegerKey(256))
+                );
+                assert_eq!(
+                    tx.get::<Large>(IntegerKey(u64::MAX)).unwrap(),
+                    Some(IntegerKey(u64::MAX))
+                );
+
+                let small: Vec<_> = tx
+                    .cursor_read::<Small>()
+                    .unwrap()
+                    .walk(None)
+                    .unwrap()
+                    .map(|row| row.unwrap())
+                    .map(|(key, value)| (u64::from(key.0), value.0))
+                    .collect();
+                let large: Vec<_> = tx
+                    .cursor_read::<Large>()
+                    .unwrap()
+                    .walk(None)
+                    .unwrap()
+                    .map(|row| row.unwrap())
+                    .map(|(key, value)| (key.0, value.0))
+                    .collect();
+                (small, large)
+            })
+            .unwrap();
+
+        // Keys are walked in numeric order.
+        let expected: Vec<_> = sorted.iter().map(|&key| (key, key)).collect();
+        assert_eq!(small, expected);
+        assert_eq!(large[..large.len() - 1], expected);
+        assert_eq!(large.last(), Some(&(u64::MAX, u64::MAX)));
+    }
+
+    #[test]
+    fn delete_range_of_integer_keys() {
+        let (_dir, db) = test_utils::provider::<TxTables>();
+        db.write(|tx| {
+            for key in 0..600 {
+                tx.put::<Large>(IntegerKey(key), IntegerKey(key))?;
+            }
+            Ok::<_, DatabaseError>(())
+        })
+        .unwrap()
+        .unwrap();
+
+        let deleted = db
+            .write(|tx| {
+                let mut deleted = tx.delete_range::<Large>(IntegerKey(100)..IntegerKey(300))?;
+                deleted += tx.delete_range::<Large>((
+                    Bound::Excluded(IntegerKey(400)),
+                    Bound::Included(IntegerKey(512)),
+                ))?;
+                Ok::<_, DatabaseError>(deleted)
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(deleted, 200 + 112);
+
+        let keys: Vec<u64> = db
+            .read(|tx| {
+                tx.cursor_read::<Large>()
+                    .unwrap()
+                    .walk(None)
+                    .unwrap()
+                    .map(|row| row.unwrap().0.0)
+                    .collect()
+            })
+            .unwrap();
+        let expected: Vec<u64> = (0..100).chain(300..=400).chain(513..600).collect();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn drop_table_deletes_the_table() {
+        let (_dir, db) = test_utils::provider::<TxTables>();
+        db.write(|tx| tx.put::<Plain>(1, IntegerKey(1)))
+            .unwrap()
+            .unwrap();
+
+        // SAFETY: no cursor is open on `Plain`.
+        db.write(|tx| unsafe { tx.drop_table::<Plain>() })
+            .unwrap()
+            .unwrap();
+
+        db.read(|tx| {
+            assert_eq!(tx.inner.open_db(Some("Plain")).err(), Some(Error::NotFound));
+            assert!(tx.inner.open_db(Some("Large")).is_ok());
+        })
+        .unwrap();
+        assert_eq!(db.stale_tables().unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn rename_table_moves_the_rows() {
+        let (_dir, db) = test_utils::provider::<TxTables>();
+        db.write(|tx| {
+            for key in 0..3 {
+                for dup in 0..4 {
+                    tx.put::<Dups>(IntegerKey(key), IntegerKey(dup))?;
+                }
+            }
+            tx.put::<Plain>(1, IntegerKey(1))
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(db.stale_tables().unwrap(), Vec::<String>::new());
+
+        // SAFETY: no cursor is open on `Dups`.
+        let err = db
+            .write(|tx| unsafe { tx.rename_table("Dups", "Plain") })
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(err, DatabaseError::CreateTable(_)), "{err:?}");
+
+        // SAFETY: no cursor is open on `Dups`.
+        db.write(|tx| unsafe { tx.rename_table("Dups", "Renamed") })
+            .unwrap()
+            .unwrap();
+
+        let rows = db
+            .read(|tx| {
+                assert_eq!(tx.inner.open_db(Some("Dups")).err(), Some(Error::NotFound));
+                let renamed = tx.inner.open_db(Some("Renamed")).unwrap();
+                assert_eq!(tx.inner.db_flags(&renamed).unwrap(), Dups.database_flags());
+
+                tx.cursor_dup_read::<Renamed>()
+                    .unwrap()
+                    .walk(None)
+                    .unwrap()
+                    .map(|row| row.map(|(key, dup)| (key.0, dup.0)))
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap()
+            })
+            .unwrap();
+        let expected: Vec<_> = (0..3)
+            .flat_map(|key| (0..4).map(move |dup| (key, dup)))
+            .collect();
+        assert_eq!(rows, expected);
+
+        // `Dups` is missing until the next open, and `Renamed` isn't part of the set.
+        assert_eq!(db.stale_tables().unwrap(), ["Renamed"]);
+    }
+
+    #[test]
+    fn compare_and_swap_only_replaces_the_expected_value() {
+        let (_dir, db) = test_utils::provider::<TxTables>();
+        db.write(|tx| tx.put::<Plain>(1, IntegerKey(10)))
+            .unwrap()
+            .unwrap();
+
+        db.write(|tx| {
+            let swap = |key, expected: Option<u64>, new: Option<u64>| {
+                tx.compare_and_swap::<Plain>(key, expected.map(IntegerKey), new.map(IntegerKey))
+                    .unwrap()
+            };
+
+            assert!(swap(1, Some(10), Some(11)));
+            // A stale or missing expected value leaves the row untouched.
+            assert!(!swap(1, Some(10), Some(12)));
+            assert!(!swap(1, None, Some(12)));
+            assert_eq!(tx.get::<Plain>(1).unwrap(), Some(IntegerKey(11)));
+
+            // A missing row only matches `None`.
+            assert!(!swap(2, Some(10), Some(20)));
+            assert_eq!(tx.get::<Plain>(2).unwrap(), None);
+            assert!(swap(2, None, Some(20)));
+            assert!(swap(2, Some(20), None));
+        })
+        .unwrap();
+
+        db.read(|tx| {
+            assert_eq!(tx.get::<Plain>(1).unwrap(), Some(IntegerKey(11)));
+            assert_eq!(tx.get::<Plain>(2).unwrap(), None);
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn update_stores_what_the_closure_returns() {
+        let (_dir, db) = test_utils::provider::<TxTables>();
+        db.write(|tx| tx.put::<Plain>(1, IntegerKey(10)))
+            .unwrap()
+            .unwrap();
+
+        db.write(|tx| {
+            tx.update::<Plain, _>(1, |value| {
+                value.map(|IntegerKey(value)| IntegerKey(value + 1))
+            })?;
+            tx.update::<Plain, _>(2, |value| {
+                assert_eq!(value, None);
+                Some(IntegerKey(20))
+            })?;
+            // Returning `None` deletes the row, and is a no-op on a missing one.
+            tx.update::<Plain, _>(2, |_| None)?;
+            tx.update::<Plain, _>(3, |_| None)
+        })
+        .unwrap()
+        .unwrap();
+
+        db.read(|tx| {
+            assert_eq!(tx.get::<Plain>(1).unwrap(), Some(IntegerKey(11)));
+            assert_eq!(tx.get::<Plain>(2).unwrap(), None);
+            assert_eq!(tx.get::<Plain>(3).unwrap(), None);
+        })
+        .unwrap();
+
+        let err = db
+            .write(|tx| tx.update::<Dups, _>(IntegerKey(1), |_| None))
+            .unwrap()
+            .unwrap_err();
+        assert!(err.to_string().contains("DUPSORT table Dups"), "{err}");
+    }
+
+    #[test]
+    fn estimate_range_counts_the_rows_at_the_bounds() {
+        let (_dir, db) = test_utils::provider::<TxTables>();
+        // Small enough to fit a single page, where the estimate is exact.
+        db.write(|tx| {
+            for key in 0..100 {
+                tx.put::<Plain>(key * 2, IntegerKey(key))?;
+            }
+            for key in 0..10 {
+                for dup in 0..3 {
+                    tx.put::<Dups>(IntegerKey(key), IntegerKey(dup))?;
+                }
+            }
+            Ok::<_, DatabaseError>(())
+        })
+        .unwrap()
+        .unwrap();
+
+        db.read(|tx| {
+            let plain = |start: Bound<u64>, end: Bound<u64>| {
+                tx.estimate_range::<Plain>((start, end)).unwrap()
+            };
+            assert_eq!(plain(Bound::Unbounded, Bound::Unbounded), 100);
+            assert_eq!(plain(Bound::Included(10), Bound::Excluded(20)), 5);
+            assert_eq!(plain(Bound::Included(10), Bound::Included(20)), 6);
+            assert_eq!(plain(Bound::Excluded(10), Bound::Included(20)), 5);
+            assert_eq!(plain(Bound::Excluded(10), Bound::Excluded(20)), 4);
+            // Bounds without a row aren't adjusted for.
+            assert_eq!(plain(Bound::Excluded(11), Bound::Included(19)), 4);
+            assert_eq!(plain(Bound::Unbounded, Bound::Included(198)), 100);
+            assert_eq!(plain(Bound::Excluded(198), Bound::Unbounded), 0);
+
+            let dups = |start: Bound<u64>, end: Bound<u64>| {
+                tx.estimate_range::<Dups>((start.map(IntegerKey), end.map(IntegerKey)))
+                    .unwrap()
+            };
+            assert_eq!(dups(Bound::Included(2), Bound::Included(4)), 9);
+            assert_eq!(dups(Bound::Excluded(2), Bound::Excluded(4)), 3);
+
+            let mut first = tx.cursor_read::<Plain>().unwrap();
+            let mut second = tx.cursor_read::<Plain>().unwrap();
+            first.seek(10).unwrap();
+            second.seek(19).unwrap();
+            assert_eq!(first.estimate_distance(&second).unwrap(), 5);
+            assert_eq!(second.estimate_distance(&first).unwrap(), -5);
+        })
+        .unwrap();
+
+        db.write(|tx| {
+            for key in 0..100_000 {
+                tx.put::<Large>(IntegerKey(key), IntegerKey(key))?;
+            }
+            Ok::<_, DatabaseError>(())
+        })
+        .unwrap()
+        .unwrap();
+
+        db.read(|tx| {
+            for (start, end) in [
+                (0, 100_000),
+                (1_000, 51_000),
+                (30_000, 30_500),
+                (99_000, 100_000),
+            ] {
+                let estimate = tx
+                    .estimate_range::<Large>(IntegerKey(start)..IntegerKey(end))
+                    .unwrap() as u64;
+                let count = end - start;
+                assert!(
+                    estimate.abs_diff(count) <= count / 10 + 200,
+                    "{start}..{end}: estimated {estimate} rows"
+                );
+            }
+        })
+        .unwrap();
+    }
+}
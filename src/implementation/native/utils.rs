@@ -1,9 +1,15 @@
 use std::borrow::Cow;
 
 use reth_db::{
-    DatabaseError,
+    DatabaseError, DatabaseWriteOperation,
     table::{Compress, Decode, Decompress, Encode, Table, TableRow},
 };
+use reth_storage_errors::db::{DatabaseErrorInfo, DatabaseWriteError};
+
+use crate::{
+    error::{self, ErrorContext, Operation},
+    traits::take_codec_error,
+};
 
 /// Helper function to decode a `(key, value)` pair.
 pub(crate) fn decoder<'a, T>(
@@ -49,15 +55,195 @@ where
     })
 }
 
-pub(crate) fn uncompressable_ref_util<T: Table>(
+/// Operation a row is encoded for, deciding how a codec failure is returned.
+pub(crate) trait EncodeOperation: Copy {
+    /// Error of a codec failure while encoding the row of `T` stored under `key`.
+    fn codec_error<T: Table>(self, key: &[u8], error: DatabaseError) -> DatabaseError;
+}
+
+/// Writes fail with a [`DatabaseWriteError`] on the table.
+impl EncodeOperation for DatabaseWriteOperation {
+    fn codec_error<T: Table>(self, key: &[u8], error: DatabaseError) -> DatabaseError {
+        DatabaseWriteError {
+            info: DatabaseErrorInfo {
+                message: error.to_string().into(),
+                code: libmdbx_native::Error::DecodeError.to_err_code(),
+            },
+            operation: self,
+            table_name: T::NAME,
+            key: key.to_vec(),
+        }
+        .into()
+    }
+}
+
+/// Reads and deletes fail with a codec [`error::Error`] carrying the table and key.
+impl EncodeOperation for Operation {
+    fn codec_error<T: Table>(self, key: &[u8], error: DatabaseError) -> DatabaseError {
+        let context = ErrorContext::new(self).with_table(T::NAME).with_key(key);
+        error::Error::Codec(Box::new(error), context).into()
+    }
+}
+
+/// Encodes `key` of `T` for `operation`.
+///
+/// [`Encode`] and [`Compress`] can't fail, so codecs report their failures with
+/// [`report_codec_error`](crate::report_codec_error). A reported failure is returned here as the
+/// error of `operation` on the table.
+pub(crate) fn encode_key<T: Table>(
+    operation: impl EncodeOperation,
+    key: T::Key,
+) -> Result<Vec<u8>, DatabaseError> {
+    take_codec_error();
+    let encoded: Vec<u8> = key.encode().into();
+
+    codec_result::<T>(operation, &encoded).map(|()| encoded)
+}
+
+/// Compresses `value` of the row of `T` stored under the encoded `key`, failing like
+/// [`encode_key`].
+pub(crate) fn compress_value<T: Table>(
+    operation: impl EncodeOperation,
+    key: &[u8],
+    value: &T::Value,
+) -> Result<Vec<u8>, DatabaseError> {
+    take_codec_error();
+    let compressed = match value.uncompressable_ref() {
+        Some(value) => value.to_vec(),
+        None => {
+            let mut buf = Vec::new();
+            value.compress_to_buf(&mut buf);
+            buf
+        }
+    };
+
+    codec_result::<T>(operation, key).map(|()| compressed)
+}
+
+/// Encodes a row of `T` for `operation` with [`encode_key`] and [`compress_value`].
+pub(crate) fn encode_row<T: Table>(
+    operation: impl EncodeOperation,
     key: T::Key,
     value: &T::Value,
-) -> (Vec<u8>, Vec<u8>) {
-    if let Some(val) = value.uncompressable_ref() {
-        (key.encode().into(), val.to_vec())
-    } else {
-        let mut buf = Vec::new();
-        value.compress_to_buf(&mut buf);
-        (key.encode().into(), buf.to_vec())
+) -> Result<(Vec<u8>, Vec<u8>), DatabaseError> {
+    let key = encode_key::<T>(operation, key)?;
+    let value = compress_value::<T>(operation, &key, value)?;
+
+    Ok((key, value))
+}
+
+/// Takes the failure reported by the codecs while encoding the row stored under `key`.
+fn codec_result<T: Table>(
+    operation: impl EncodeOperation,
+    key: &[u8],
+) -> Result<(), DatabaseError> {
+    match take_codec_error() {
+        None => Ok(()),
+        Some(e) => Err(operation.codec_error::<T>(key, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BufMut;
+    use reth_db::{
+        DatabaseError, DatabaseWriteOperation,
+        cursor::DbCursorRO,
+        table::{Compress, Decode, Decompress, Encode},
+        transaction::{DbTx, DbTxMut},
+    };
+    use serde::{Deserialize, Serialize};
+
+    use crate::{IntegerKey, report_codec_error, test_utils};
+
+    /// A value whose compression always fails.
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Unencodable;
+
+    impl Compress for Unencodable {
+        type Compressed = Vec<u8>;
+
+        fn compress_to_buf<B: BufMut + AsMut<[u8]>>(&self, _: &mut B) {
+            report_codec_error("compress", "unsupported value");
+        }
+    }
+
+    impl Decompress for Unencodable {
+        fn decompress(_: &[u8]) -> Result<Self, DatabaseError> {
+            Ok(Self)
+        }
+    }
+
+    /// A key whose encoding always fails.
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+    struct UnencodableKey(u64);
+
+    impl Encode for UnencodableKey {
+        type Encoded = [u8; 8];
+
+        fn encode(self) -> Self::Encoded {
+            report_codec_error("encode", "unsupported key");
+            self.0.encode()
+        }
+    }
+
+    impl Decode for UnencodableKey {
+        fn decode(value: &[u8]) -> Result<Self, DatabaseError> {
+            u64::decode(value).map(Self)
+        }
+    }
+
+    db_table!((Unencodables) | u64, Unencodable);
+    db_table!((UnencodableKeys) | UnencodableKey, IntegerKey<u64>);
+
+    tables!(UnencodableTables, 2, [Unencodables, UnencodableKeys]);
+
+    #[test]
+    fn codec_failure_is_a_write_error() {
+        let (_dir, db) = test_utils::provider::<UnencodableTables>();
+
+        match db
+            .write(|tx| tx.put::<Unencodables>(7, Unencodable))
+            .unwrap()
+        {
+            Err(DatabaseError::Write(err)) => {
+                assert_eq!(err.table_name, "Unencodables");
+                assert_eq!(err.operation, DatabaseWriteOperation::Put);
+                assert_eq!(err.key, 7u64.encode());
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+
+        let rows = db.read(|tx| tx.entries::<Unencodables>()).unwrap().unwrap();
+        assert_eq!(rows, 0);
+    }
+
+    #[test]
+    fn codec_failure_of_a_read_names_the_operation() {
+        let (_dir, db) = test_utils::provider::<UnencodableTables>();
+
+        let message = |result: Result<_, DatabaseError>| match result {
+            Err(DatabaseError::Other(message)) => message,
+            other => panic!("unexpected result {other:?}"),
+        };
+        let key = "on table UnencodableKeys with key 0x0000000000000007";
+
+        let get = db
+            .read(|tx| tx.get::<UnencodableKeys>(UnencodableKey(7)))
+            .unwrap();
+        assert!(message(get.map(|_| ())).starts_with(&format!("get {key}")));
+
+        let seek = db
+            .read(|tx| {
+                tx.cursor_read::<UnencodableKeys>()?
+                    .seek_exact(UnencodableKey(7))
+            })
+            .unwrap();
+        assert!(message(seek.map(|_| ())).starts_with(&format!("cursor {key}")));
+
+        let delete = db
+            .write(|tx| tx.delete::<UnencodableKeys>(UnencodableKey(7), None))
+            .unwrap();
+        assert!(message(delete.map(|_| ())).starts_with(&format!("delete {key}")));
     }
 }
@@ -0,0 +1,99 @@
+//! Pool of parked read transactions, see [`DatabaseArguments::with_read_txn_pool_size`].
+//!
+//! [`DatabaseArguments::with_read_txn_pool_size`]: super::DatabaseArguments::with_read_txn_pool_size
+
+use libmdbx_native::{Environment, Error, ParkedTransaction, RO, Transaction};
+use parking_lot::Mutex;
+
+/// Read transactions parked with `mdbx_txn_reset`, renewed on checkout instead of beginning new
+/// ones.
+#[derive(Debug)]
+pub(crate) struct ReadTxnPool {
+    /// Maximum number of parked transactions, the pool is disabled when zero.
+    capacity: usize,
+    parked: Mutex<Vec<ParkedTransaction>>,
+}
+
+impl ReadTxnPool {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            parked: Mutex::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Renews a parked transaction, or begins a new one if none can be renewed.
+    pub(crate) fn checkout(&self, env: &Environment) -> Result<Transaction<RO>, Error> {
+        loop {
+            let Some(parked) = self.parked.lock().pop() else {
+                return env.begin_ro_txn();
+            };
+
+            // A transaction that fails to renew is aborted, try the next one.
+            if let Ok(txn) = parked.renew() {
+                return Ok(txn);
+            }
+        }
+    }
+
+    /// Parks the transaction for a later checkout. Returns it back if the pool is disabled or
+    /// full.
+    pub(crate) fn release(&self, txn: Transaction<RO>) -> Option<Transaction<RO>> {
+        if self.parked.lock().len() >= self.capacity {
+            return Some(txn);
+        }
+
+        // Cursors still open on the transaction keep it from being parked, it's aborted then.
+        if let Ok(parked) = txn.reset() {
+            let mut pool = self.parked.lock();
+            if pool.len() < self.capacity {
+                pool.push(parked);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libmdbx_native::WriteFlags;
+
+    use super::*;
+
+    #[test]
+    fn renews_released_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = Environment::builder().open(dir.path()).unwrap();
+        let pool = ReadTxnPool::new(1);
+
+        let txn = pool.checkout(&env).unwrap();
+        let parked_id = txn.id().unwrap();
+        assert!(pool.release(txn).is_none());
+        assert_eq!(pool.parked.lock().len(), 1);
+
+        let rw = env.begin_rw_txn().unwrap();
+        let db = rw.open_db(None).unwrap();
+        rw.put(db.dbi(), b"key", b"value", WriteFlags::empty())
+            .unwrap();
+        rw.commit().unwrap();
+
+        // The parked transaction is renewed on the snapshot of the write.
+        let txn = pool.checkout(&env).unwrap();
+        assert!(pool.parked.lock().is_empty());
+        assert!(txn.id().unwrap() > parked_id);
+        assert_eq!(
+            txn.get::<Vec<u8>>(db.dbi(), b"key").unwrap(),
+            Some(b"value".to_vec())
+        );
+
+        // Once the pool is full, transactions are handed back to be committed.
+        let other = pool.checkout(&env).unwrap();
+        assert!(pool.release(txn).is_none());
+        assert!(pool.release(other).is_some());
+        assert_eq!(pool.parked.lock().len(), 1);
+
+        let disabled = ReadTxnPool::new(0);
+        assert!(disabled.release(env.begin_ro_txn().unwrap()).is_some());
+    }
+}
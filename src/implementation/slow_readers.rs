@@ -0,0 +1,228 @@
+//! Handling of the read transactions that keep the database from reusing pages.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use libmdbx_native::{HandleSlowReadersReturnCode, ffi};
+use parking_lot::RwLock;
+use tracing::{debug, warn};
+
+use super::env::GIGABYTE;
+
+/// Space that a read-only transaction can occupy until the warning is emitted.
+/// See [`libmdbx_native::EnvironmentBuilder::set_handle_slow_readers`] for more information.
+const MAX_SAFE_READER_SPACE: usize = 10 * GIGABYTE;
+
+/// State of the environments opened by this process, keyed by their raw pointer.
+static ENVIRONMENTS: LazyLock<RwLock<HashMap<usize, Arc<SlowReaders>>>> =
+    LazyLock::new(Default::default);
+
+/// What to do when an old read transaction keeps the database from reusing pages, before the
+/// database grows instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SlowReaderPolicy {
+    /// Logs the reader and lets the database grow.
+    #[default]
+    Log,
+    /// Waits `delay` for the reader to finish, at most `retries` times, before letting the
+    /// database grow.
+    WaitAndRetry { delay: Duration, retries: u32 },
+    /// Clears the slot of the reader if it belongs to this process, its transaction then fails.
+    /// Readers of other processes are only logged.
+    KillInProcess,
+    /// Clears the slot of any reader retaining more than `max_space` bytes, whichever process it
+    /// belongs to.
+    ///
+    /// The reader itself is not stopped: a reader of another process keeps reading pages that the
+    /// database may now reuse, so that process has to be stopped by other means.
+    KillAbove { max_space: usize },
+}
+
+/// Slow reader policy and counters of an environment.
+#[derive(Debug, Default)]
+pub(crate) struct SlowReaders {
+    policy: SlowReaderPolicy,
+    /// Number of times a slow reader kept pages from being reused.
+    events: AtomicU64,
+    /// Number of reader slots cleared by the policy.
+    cleared: AtomicU64,
+}
+
+impl SlowReaders {
+    /// Number of times a slow reader kept the database from reusing pages.
+    pub(crate) fn events(&self) -> u64 {
+        self.events.load(Ordering::Relaxed)
+    }
+
+    /// Number of reader slots cleared by the policy.
+    pub(crate) fn cleared(&self) -> u64 {
+        self.cleared.load(Ordering::Relaxed)
+    }
+}
+
+/// Applies `policy` to the environment behind `env`.
+pub(crate) fn register(env: *mut ffi::MDBX_env, policy: SlowReaderPolicy) -> Arc<SlowReaders> {
+    let state = Arc::new(SlowReaders {
+        policy,
+        ..Default::default()
+    });
+    ENVIRONMENTS.write().insert(env as usize, state.clone());
+
+    state
+}
+
+/// Forgets the environment behind `env`, which falls back to the default policy.
+pub(crate) fn unregister(env: *mut ffi::MDBX_env) {
+    ENVIRONMENTS.write().remove(&(env as usize));
+}
+
+fn is_current_process(id: u32) -> bool {
+    id == std::process::id()
+}
+
+/// What to do about a slow reader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    /// Let the database grow.
+    Proceed,
+    /// Wait for the given delay, then check the reader again.
+    Wait(Duration),
+    /// Clear the slot of the reader.
+    Clear,
+}
+
+/// Applies `policy` to a reader retaining `space` bytes, checked for the `retry`th time.
+fn decide(policy: SlowReaderPolicy, in_process: bool, space: usize, retry: u32) -> Action {
+    match policy {
+        SlowReaderPolicy::WaitAndRetry { delay, retries } if retry < retries => Action::Wait(delay),
+        SlowReaderPolicy::KillInProcess if in_process => Action::Clear,
+        SlowReaderPolicy::KillAbove { max_space } if space > max_space => Action::Clear,
+        _ => Action::Proceed,
+    }
+}
+
+/// Slow readers callback handed to MDBX, applying the policy registered for the environment.
+#[allow(clippy::too_many_arguments)]
+pub(crate) extern "C" fn handle_slow_readers(
+    env: *const ffi::MDBX_env,
+    _txn: *const ffi::MDBX_txn,
+    process_id: ffi::mdbx_pid_t,
+    thread_id: ffi::mdbx_tid_t,
+    read_txn_id: u64,
+    gap: std::ffi::c_uint,
+    space: usize,
+    retry: std::ffi::c_int,
+) -> HandleSlowReadersReturnCode {
+    // A negative retry only notifies the end of the handling loop.
+    if retry < 0 {
+        return HandleSlowReadersReturnCode::ProceedWithoutKillingReader;
+    }
+
+    let state = ENVIRONMENTS.read().get(&(env as usize)).cloned();
+    let policy = state.as_ref().map(|state| state.policy).unwrap_or_default();
+    if let Some(state) = &state {
+        state.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let in_process = is_current_process(process_id as u32);
+    match decide(policy, in_process, space, retry as u32) {
+        Action::Proceed => (),
+        Action::Wait(delay) => {
+            debug!(
+                target: "libmdbx_bindings::slow_readers",
+                process_id, thread_id = thread_id as u64, read_txn_id, gap, space, retry,
+                "Waiting for a slow reader"
+            );
+            std::thread::sleep(delay);
+            return HandleSlowReadersReturnCode::Success;
+        }
+        Action::Clear => {
+            warn!(
+                target: "libmdbx_bindings::slow_readers",
+                process_id, thread_id = thread_id as u64, read_txn_id, gap, space,
+                "Clearing the reader slot of a slow reader"
+            );
+            if let Some(state) = &state {
+                state.cleared.fetch_add(1, Ordering::Relaxed);
+            }
+            return HandleSlowReadersReturnCode::ClearReaderSlot;
+        }
+    }
+
+    if space > MAX_SAFE_READER_SPACE {
+        let message = if in_process {
+            "Current process has a long-lived database transaction that grows the database file."
+        } else {
+            "External process has a long-lived database transaction that grows the database file. \
+             Use shorter-lived read transactions or shut down the node."
+        };
+        warn!(
+            target: "libmdbx_bindings::slow_readers",
+            process_id, thread_id = thread_id as u64, read_txn_id, gap, space, "{message}"
+        );
+    } else {
+        debug!(
+            target: "libmdbx_bindings::slow_readers",
+            process_id, thread_id = thread_id as u64, read_txn_id, gap, space,
+            "Slow reader keeps the database from reusing pages"
+        );
+    }
+
+    HandleSlowReadersReturnCode::ProceedWithoutKillingReader
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_never_clears() {
+        for in_process in [false, true] {
+            assert_eq!(
+                decide(SlowReaderPolicy::Log, in_process, usize::MAX, 0),
+                Action::Proceed
+            );
+        }
+    }
+
+    #[test]
+    fn wait_and_retry_gives_up_after_retries() {
+        let delay = Duration::from_millis(5);
+        let policy = SlowReaderPolicy::WaitAndRetry { delay, retries: 2 };
+
+        assert_eq!(decide(policy, false, 0, 0), Action::Wait(delay));
+        assert_eq!(decide(policy, true, 0, 1), Action::Wait(delay));
+        assert_eq!(decide(policy, false, 0, 2), Action::Proceed);
+        assert_eq!(decide(policy, true, 0, 3), Action::Proceed);
+    }
+
+    #[test]
+    fn kill_in_process_only_clears_own_readers() {
+        let policy = SlowReaderPolicy::KillInProcess;
+
+        assert_eq!(decide(policy, true, 0, 0), Action::Clear);
+        assert_eq!(decide(policy, false, usize::MAX, 0), Action::Proceed);
+    }
+
+    #[test]
+    fn kill_above_compares_space() {
+        let policy = SlowReaderPolicy::KillAbove { max_space: 100 };
+
+        assert_eq!(decide(policy, false, 100, 0), Action::Proceed);
+        assert_eq!(decide(policy, false, 101, 0), Action::Clear);
+        assert_eq!(decide(policy, true, 101, 0), Action::Clear);
+    }
+
+    #[test]
+    fn current_process_is_not_its_parent() {
+        assert!(is_current_process(std::process::id()));
+        #[cfg(unix)]
+        assert!(!is_current_process(std::os::unix::process::parent_id()));
+    }
+}
@@ -0,0 +1,69 @@
+//! Native-endian integers of `INTEGER_KEY` and `INTEGER_DUP` tables, see
+//! [`db_table!`](crate::db_table).
+
+use bytes::BufMut;
+use reth_db::{
+    DatabaseError,
+    table::{Compress, Decode, Decompress, Encode},
+};
+use serde::{Deserialize, Serialize};
+
+/// A `u32` or `u64` stored in native byte order and fixed width, as MDBX compares the keys of
+/// tables created with [`DatabaseFlags::INTEGER_KEY`](libmdbx_native::DatabaseFlags::INTEGER_KEY)
+/// and the values of tables created with
+/// [`DatabaseFlags::INTEGER_DUP`](libmdbx_native::DatabaseFlags::INTEGER_DUP).
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct IntegerKey<N>(pub N);
+
+macro_rules! integer_key {
+    ($int:ty) => {
+        impl From<$int> for IntegerKey<$int> {
+            fn from(value: $int) -> Self {
+                Self(value)
+            }
+        }
+
+        impl From<IntegerKey<$int>> for $int {
+            fn from(value: IntegerKey<$int>) -> Self {
+                value.0
+            }
+        }
+
+        impl Encode for IntegerKey<$int> {
+            type Encoded = [u8; size_of::<$int>()];
+
+            fn encode(self) -> Self::Encoded {
+                self.0.to_ne_bytes()
+            }
+        }
+
+        impl Decode for IntegerKey<$int> {
+            fn decode(value: &[u8]) -> Result<Self, DatabaseError> {
+                value
+                    .try_into()
+                    .map(|bytes| Self(<$int>::from_ne_bytes(bytes)))
+                    .map_err(|_| DatabaseError::Decode)
+            }
+        }
+
+        impl Compress for IntegerKey<$int> {
+            type Compressed = Vec<u8>;
+
+            fn compress_to_buf<B: BufMut + AsMut<[u8]>>(&self, buf: &mut B) {
+                buf.put_slice(&self.0.to_ne_bytes());
+            }
+        }
+
+        impl Decompress for IntegerKey<$int> {
+            fn decompress(value: &[u8]) -> Result<Self, DatabaseError> {
+                Self::decode(value)
+            }
+        }
+    };
+}
+
+integer_key!(u32);
+integer_key!(u64);
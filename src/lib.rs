@@ -1,22 +1,48 @@
+pub(crate) mod error;
+pub(crate) mod expiry;
 pub(crate) mod implementation;
+pub(crate) mod integer_key;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub(crate) mod provider;
+pub(crate) mod registry;
+pub(crate) mod snapshot;
 #[macro_use]
 pub(crate) mod tables;
+#[cfg(test)]
+pub(crate) mod test_utils;
+pub(crate) mod trace;
 pub(crate) mod traits;
 #[macro_use]
 pub(crate) mod codecs;
 
+// The exported macros refer to the crate by name, the unit tests use them from the inside.
+#[cfg(test)]
+extern crate self as libmdbx_bindings;
+
 pub use bytes::BufMut;
-pub use implementation::LibmdbxTx;
-pub use libmdbx_native::{RO, RW};
+pub use error::{Error, ErrorContext, Operation};
+pub use expiry::{ReaperConfig, ReaperHandle};
+pub use implementation::{
+    DatabaseArguments, DynDecoder, DynTable, LibmdbxCursor, LibmdbxTx, RenewingWalker,
+    SlowReaderPolicy,
+};
+pub use integer_key::IntegerKey;
+pub use libmdbx_native::{
+    Canary, CheckError, CheckReport, Cursor, DatabaseFlags, RO, RW, ReaderInfo, TableReport,
+};
 pub use provider::LibmdbxProvider;
-pub use reth_db::table::Table;
+pub use registry::TableRegistry;
 pub use reth_db::table::{Compress, Decompress};
+pub use reth_db::table::{DupSort, Table};
 pub use reth_db::{
     DatabaseError, TableType,
     cursor::{DbCursorRO, DbCursorRW},
     transaction::{DbTx, DbTxMut},
 };
+pub use snapshot::Snapshot;
+#[cfg(feature = "trace-ops")]
+pub use trace::{TraceLevel, set_trace_level, trace_level};
 
 pub use traits::*;
 
@@ -34,8 +60,8 @@ mod re_exports {
     };
 
     pub use alloy_primitives::bytes::BufMut as AlloyBytesMut;
-    pub use alloy_rlp::Result as RlpResult;
     pub use alloy_rlp::{Decodable, Encodable};
+    pub use alloy_rlp::{Error as RlpError, Result as RlpResult};
     pub use libmdbx_bindings_derive::derive_libmdbx_value;
 
     pub use reth_db_api::table::{Decode, Encode};
@@ -0,0 +1,403 @@
+//! Instrumentation of the transactions, reported to a [`MetricsSink`].
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use libmdbx_native::{CommitLatency, Environment, PageOps};
+use parking_lot::Mutex;
+
+/// Receives the measurements taken by the transactions of an environment.
+///
+/// Every method does nothing by default, so that a sink only implements what it exports.
+pub trait MetricsSink: Debug + Send + Sync {
+    /// Called after every get, put and delete of a table.
+    fn record_operation(&self, table: &'static str, operation: MetricOp, latency: Duration) {
+        let _ = (table, operation, latency);
+    }
+
+    /// Called when a transaction is committed, aborted or dropped.
+    fn record_transaction(
+        &self,
+        mode: TransactionMode,
+        outcome: TransactionOutcome,
+        lifetime: Duration,
+    ) {
+        let _ = (mode, outcome, lifetime);
+    }
+
+    /// Called after every successful commit of a read-write transaction.
+    fn record_commit(&self, latency: CommitMetrics) {
+        let _ = latency;
+    }
+
+    /// Called after every successful commit of a read-write transaction, with the state of the
+    /// environment.
+    fn record_environment(&self, map_size: usize, page_ops: &PageOps) {
+        let _ = (map_size, page_ops);
+    }
+}
+
+/// Operation on a table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MetricOp {
+    /// Read of a single row.
+    Get,
+    /// Write of a single row.
+    Put,
+    /// Deletion of a row.
+    Delete,
+}
+
+/// Kind of a transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransactionMode {
+    /// Read-only transaction.
+    ReadOnly,
+    /// Read-write transaction.
+    ReadWrite,
+}
+
+/// How a transaction ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransactionOutcome {
+    /// The transaction was committed.
+    Commit,
+    /// The transaction was aborted or dropped, including when its commit failed.
+    Abort,
+    /// The read-only transaction was parked in the read transaction pool for reuse, see
+    /// [`DatabaseArguments::with_read_txn_pool_size`](crate::DatabaseArguments::with_read_txn_pool_size).
+    Release,
+}
+
+/// Latency breakdown of a commit, see [`CommitLatency`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CommitMetrics {
+    /// Commit of child transactions, update of the table records and destruction of the cursors.
+    pub preparation: Duration,
+    /// Update of the free list, by wall clock.
+    pub gc: Duration,
+    /// Writing of the dirty pages.
+    pub write: Duration,
+    /// Syncing of the written pages to the disk.
+    pub sync: Duration,
+    /// Whole commit.
+    pub whole: Duration,
+}
+
+impl From<&CommitLatency> for CommitMetrics {
+    fn from(latency: &CommitLatency) -> Self {
+        Self {
+            preparation: latency.preparation(),
+            gc: latency.gc_wallclock(),
+            write: latency.write(),
+            sync: latency.sync(),
+            whole: latency.whole(),
+        }
+    }
+}
+
+/// Number and total latency of some measured event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timings {
+    /// Number of events.
+    pub count: u64,
+    /// Sum of the durations of the events.
+    pub total: Duration,
+    /// Longest event.
+    pub max: Duration,
+}
+
+impl Timings {
+    fn record(&mut self, duration: Duration) {
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+}
+
+/// [`MetricsSink`] keeping everything in memory, mostly useful in tests.
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics {
+    inner: Mutex<Recorded>,
+}
+
+/// Everything recorded by an [`InMemoryMetrics`].
+#[derive(Clone, Debug, Default)]
+pub struct Recorded {
+    /// Operations per table.
+    pub operations: HashMap<(&'static str, MetricOp), Timings>,
+    /// Transaction lifetimes.
+    pub transactions: HashMap<(TransactionMode, TransactionOutcome), Timings>,
+    /// Latency breakdown of every commit, oldest first.
+    pub commits: Vec<CommitMetrics>,
+    /// Map size after the last commit.
+    pub map_size: usize,
+    /// Page operations after the last commit.
+    pub page_ops: Option<PageOps>,
+}
+
+impl InMemoryMetrics {
+    /// Returns a copy of everything recorded so far.
+    pub fn snapshot(&self) -> Recorded {
+        self.inner.lock().clone()
+    }
+
+    /// Returns the timings of `operation` on `table`.
+    pub fn operation(&self, table: &str, operation: MetricOp) -> Timings {
+        self.inner
+            .lock()
+            .operations
+            .iter()
+            .find(|((name, op), _)| *name == table && *op == operation)
+            .map(|(_, timings)| *timings)
+            .unwrap_or_default()
+    }
+
+    /// Forgets everything recorded so far.
+    pub fn reset(&self) {
+        *self.inner.lock() = Recorded::default();
+    }
+}
+
+impl MetricsSink for InMemoryMetrics {
+    fn record_operation(&self, table: &'static str, operation: MetricOp, latency: Duration) {
+        self.inner
+            .lock()
+            .operations
+            .entry((table, operation))
+            .or_default()
+            .record(latency);
+    }
+
+    fn record_transaction(
+        &self,
+        mode: TransactionMode,
+        outcome: TransactionOutcome,
+        lifetime: Duration,
+    ) {
+        self.inner
+            .lock()
+            .transactions
+            .entry((mode, outcome))
+            .or_default()
+            .record(lifetime);
+    }
+
+    fn record_commit(&self, latency: CommitMetrics) {
+        self.inner.lock().commits.push(latency);
+    }
+
+    fn record_environment(&self, map_size: usize, page_ops: &PageOps) {
+        let mut inner = self.inner.lock();
+        inner.map_size = map_size;
+        inner.page_ops = Some(page_ops.clone());
+    }
+}
+
+/// Measurements of a single transaction, recording its lifetime as aborted when dropped before
+/// [`TxMetrics::commit`].
+#[derive(Debug)]
+pub(crate) struct TxMetrics {
+    sink: Arc<dyn MetricsSink>,
+    mode: TransactionMode,
+    opened: Instant,
+    committed: bool,
+}
+
+impl TxMetrics {
+    pub(crate) fn new(sink: Arc<dyn MetricsSink>, read_only: bool) -> Self {
+        let mode = if read_only {
+            TransactionMode::ReadOnly
+        } else {
+            TransactionMode::ReadWrite
+        };
+        Self {
+            sink,
+            mode,
+            opened: Instant::now(),
+            committed: false,
+        }
+    }
+
+    /// Times `f` as `operation` on `table`.
+    pub(crate) fn time<R>(
+        &self,
+        table: &'static str,
+        operation: MetricOp,
+        f: impl FnOnce() -> R,
+    ) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.sink
+            .record_operation(table, operation, start.elapsed());
+
+        result
+    }
+
+    /// Records a successful commit, along with the state of `env` for read-write transactions.
+    pub(crate) fn commit(mut self, latency: &CommitLatency, env: &Environment) {
+        self.committed = true;
+        self.sink
+            .record_transaction(self.mode, TransactionOutcome::Commit, self.opened.elapsed());
+
+        if self.mode == TransactionMode::ReadWrite {
+            self.sink.record_commit(latency.into());
+            if let Ok(info) = env.info() {
+                self.sink
+                    .record_environment(info.map_size(), &info.page_ops());
+            }
+        }
+    }
+
+    /// Records a read-only transaction ended by parking it for reuse.
+    pub(crate) fn release(mut self) {
+        self.committed = true;
+        self.sink.record_transaction(
+            self.mode,
+            TransactionOutcome::Release,
+            self.opened.elapsed(),
+        );
+    }
+}
+
+impl Drop for TxMetrics {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.sink.record_transaction(
+                self.mode,
+                TransactionOutcome::Abort,
+                self.opened.elapsed(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reth_db::{
+        ClientVersion,
+        transaction::{DbTx, DbTxMut},
+    };
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{DatabaseArguments, IntegerKey, LibmdbxProvider, test_utils};
+
+    db_table!((Measured) | u64, IntegerKey<u64>);
+
+    tables!(MeasuredTables, 1, [Measured]);
+
+    /// Opens a provider reporting to the returned sink, with a read transaction pool of
+    /// `pool_size` transactions.
+    fn provider(
+        pool_size: usize,
+    ) -> (
+        TempDir,
+        LibmdbxProvider<MeasuredTables>,
+        Arc<InMemoryMetrics>,
+    ) {
+        let metrics = Arc::new(InMemoryMetrics::default());
+        let args = DatabaseArguments::new(ClientVersion::default())
+            .with_log_level(None)
+            .with_read_txn_pool_size(pool_size)
+            .with_metrics(Some(metrics.clone() as Arc<dyn MetricsSink>));
+        let (dir, db) = test_utils::provider_with_args(args);
+
+        // Forget the transaction creating the tables.
+        metrics.reset();
+        (dir, db, metrics)
+    }
+
+    #[test]
+    fn records_operations() {
+        let (_dir, db, metrics) = provider(0);
+        db.write(|tx| {
+            for key in 1..=3 {
+                tx.put::<Measured>(key, IntegerKey(key))?;
+            }
+            tx.delete::<Measured>(1, None)
+        })
+        .unwrap()
+        .unwrap();
+        db.read(|tx| {
+            assert_eq!(tx.get::<Measured>(2).unwrap(), Some(IntegerKey(2)));
+            assert_eq!(tx.get::<Measured>(1).unwrap(), None);
+        })
+        .unwrap();
+
+        for (operation, count) in [
+            (MetricOp::Put, 3),
+            (MetricOp::Delete, 1),
+            (MetricOp::Get, 2),
+        ] {
+            let timings = metrics.operation("Measured", operation);
+            assert_eq!(timings.count, count, "{operation:?}");
+            assert!(timings.max > Duration::ZERO, "{operation:?}");
+            assert!(timings.total >= timings.max, "{operation:?}");
+        }
+        assert_eq!(
+            metrics.operation("Other", MetricOp::Get),
+            Timings::default()
+        );
+    }
+
+    #[test]
+    fn records_transactions() {
+        let (_dir, db, metrics) = provider(1);
+        db.write(|tx| tx.put::<Measured>(1, IntegerKey(1)))
+            .unwrap()
+            .unwrap();
+        db.read(|tx| tx.get::<Measured>(1)).unwrap().unwrap();
+        drop(db.snapshot().unwrap());
+
+        let recorded = metrics.snapshot();
+        let count = |mode, outcome| {
+            recorded
+                .transactions
+                .get(&(mode, outcome))
+                .map_or(0, |timings| timings.count)
+        };
+        assert_eq!(
+            count(TransactionMode::ReadWrite, TransactionOutcome::Commit),
+            1
+        );
+        assert_eq!(
+            count(TransactionMode::ReadOnly, TransactionOutcome::Release),
+            1
+        );
+        assert_eq!(
+            count(TransactionMode::ReadOnly, TransactionOutcome::Abort),
+            1
+        );
+        assert_eq!(
+            count(TransactionMode::ReadOnly, TransactionOutcome::Commit),
+            0
+        );
+
+        assert_eq!(recorded.commits.len(), 1);
+        assert!(recorded.commits[0].whole >= recorded.commits[0].write);
+        assert!(recorded.map_size > 0);
+        assert!(recorded.page_ops.is_some());
+    }
+
+    #[test]
+    fn commits_unpooled_reads() {
+        let (_dir, db, metrics) = provider(0);
+        db.read(|tx| tx.get::<Measured>(1)).unwrap().unwrap();
+
+        let recorded = metrics.snapshot();
+        let count = |outcome| {
+            recorded
+                .transactions
+                .get(&(TransactionMode::ReadOnly, outcome))
+                .map_or(0, |timings| timings.count)
+        };
+        assert_eq!(count(TransactionOutcome::Commit), 1);
+        assert_eq!(count(TransactionOutcome::Release), 0);
+        assert!(recorded.commits.is_empty());
+    }
+}
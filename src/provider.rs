@@ -1,18 +1,36 @@
 // #![allow(non_camel_case_types)]
 #![allow(private_bounds)]
 
-use std::{ffi::c_int, marker::PhantomData, path::Path};
+use std::{
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::Instant,
+};
 
 use eyre::Context;
-use libmdbx_native::{RO, RW};
+use libmdbx_native::{Canary, CheckOptions, CheckReport, RO, RW, ReaderInfo};
 use reth_db::{
-    ClientVersion, DatabaseError, is_database_empty,
+    ClientVersion, DatabaseError,
+    cursor::{DbCursorRO, RangeWalker},
+    is_database_empty,
+    table::{Decode, Table},
     transaction::DbTx,
     version::{DatabaseVersionError, check_db_version_file, create_db_version_file},
 };
+use tracing::warn;
 
 use crate::{
-    implementation::{DatabaseArguments, DatabaseEnv, DatabaseEnvKind, tx::LibmdbxTx},
+    error::{Error, ErrorContext, Operation},
+    expiry::{EXPIRY_INDEX, ReaperConfig, ReaperHandle},
+    implementation::{
+        DatabaseArguments, DatabaseEnv, DatabaseEnvKind, LibmdbxCursor,
+        tx::{LibmdbxTx, as_slice_bound, encode_bounds},
+    },
+    registry::TableRegistry,
+    snapshot::Snapshot,
     traits::TableSet,
     // tables::Tables,
 };
@@ -20,21 +38,23 @@ use crate::{
 const GIGABYTE: u64 = 1024 * 1024 * 1024;
 
 #[derive(Debug)]
-pub struct LibmdbxProvider<S>(DatabaseEnv, PhantomData<S>);
-
-#[inline]
-pub(crate) fn mdbx_result(err_code: c_int) -> eyre::Result<bool> {
-    match err_code {
-        reth_mdbx_sys::MDBX_SUCCESS => Ok(false),
-        reth_mdbx_sys::MDBX_RESULT_TRUE => Ok(true),
-        _ => Err(eyre::eyre!("shit no good")),
-    }
-}
+pub struct LibmdbxProvider<S>(Arc<DatabaseEnv>, PhantomData<S>);
 
 impl<S: TableSet> LibmdbxProvider<S> {
     /// Opens up an existing database or creates a new one at the specified
     /// path. Creates tables if necessary. Opens in read/write mode.
     pub fn init_db<P: AsRef<Path>>(path: P) -> eyre::Result<Self> {
+        Self::init_db_with_args(
+            path,
+            DatabaseArguments::new(ClientVersion::default()).with_log_level(None),
+        )
+    }
+
+    /// Same as [`LibmdbxProvider::init_db`], opening the environment with the given arguments.
+    pub fn init_db_with_args<P: AsRef<Path>>(
+        path: P,
+        args: DatabaseArguments,
+    ) -> eyre::Result<Self> {
         let rpath = path.as_ref();
         if is_database_empty(rpath) {
             std::fs::create_dir_all(rpath).wrap_err_with(|| {
@@ -48,24 +68,30 @@ impl<S: TableSet> LibmdbxProvider<S> {
             }
         }
 
-        let db = DatabaseEnv::open(
-            rpath,
-            DatabaseEnvKind::RW,
-            DatabaseArguments::new(ClientVersion::default()).with_log_level(None),
-        )?;
+        let mut registry = TableRegistry::default();
+        S::register(&mut registry);
+
+        let db = DatabaseEnv::open(rpath, DatabaseEnvKind::RW, args)?.with_table_registry(registry);
 
         db.with_raw_env_ptr(|ptr| unsafe {
-            mdbx_result(reth_mdbx_sys::mdbx_env_set_option(
-                ptr,
-                reth_mdbx_sys::MDBX_opt_sync_bytes,
-                // 2 gb
-                GIGABYTE * 2,
-            ))
+            Error::check(
+                reth_mdbx_sys::mdbx_env_set_option(
+                    ptr,
+                    reth_mdbx_sys::MDBX_opt_sync_bytes,
+                    // 2 gb
+                    GIGABYTE * 2,
+                ),
+                ErrorContext::new(Operation::SetOption),
+            )
         })?;
 
-        let this = Self(db, PhantomData);
+        let this = Self(Arc::new(db), PhantomData);
         this.create_tables()?;
 
+        for table in this.stale_tables()? {
+            warn!(target: "libmdbx_bindings", %table, "Table found on disk is not part of the table set");
+        }
+
         Ok(this)
     }
 
@@ -73,12 +99,106 @@ impl<S: TableSet> LibmdbxProvider<S> {
     fn create_tables(&self) -> Result<(), DatabaseError> {
         let tx = LibmdbxTx::new_rw_tx(&self.0)?;
         S::create_tables(&tx)?;
+        if self.0.table_registry().has_expiring_tables() {
+            tx.create_expiry_index()?;
+        }
 
         tx.commit()?;
 
         Ok(())
     }
 
+    /// Starts a background thread deleting the expired rows of the expiring tables.
+    ///
+    /// The thread runs until the returned handle is stopped or dropped.
+    pub fn start_reaper(&self, config: ReaperConfig) -> Result<ReaperHandle, DatabaseError> {
+        ReaperHandle::spawn(self.0.clone(), config)
+            .map_err(|e| DatabaseError::Other(format!("failed to spawn the reaper: {e}")))
+    }
+
+    /// Deletes every entry of `T` whose key falls in `range`, committing a write transaction every
+    /// `chunk_size` deleted entries so that huge ranges don't keep a single transaction open.
+    ///
+    /// Returns the number of deleted entries. Chunks committed before an error stay deleted.
+    pub fn delete_range<T: Table>(
+        &self,
+        range: impl RangeBounds<T::Key>,
+        chunk_size: usize,
+    ) -> Result<usize, DatabaseError> {
+        let (start, end) = encode_bounds::<T>(Operation::Delete, &range)?;
+        let chunk_size = chunk_size.max(1);
+
+        let mut deleted = 0;
+        loop {
+            let tx = self.rw_tx()?;
+            let chunk = tx.delete_encoded_range::<T>(
+                as_slice_bound(&start),
+                as_slice_bound(&end),
+                chunk_size,
+            )?;
+            tx.commit()?;
+
+            deleted += chunk;
+            if chunk < chunk_size {
+                return Ok(deleted);
+            }
+        }
+    }
+
+    /// Returns the tables stored in the database that are not part of the [`TableSet`], such as
+    /// tables left behind by a previous version of the set.
+    pub fn stale_tables(&self) -> Result<Vec<String>, DatabaseError> {
+        let dbs = self
+            .0
+            .list_dbs()
+            .map_err(|e| DatabaseError::Read(e.into()))?;
+
+        Ok(dbs
+            .into_iter()
+            .map(|db| db.name().to_string())
+            .filter(|name| name != EXPIRY_INDEX && S::from_str(name).is_err())
+            .collect())
+    }
+
+    /// Checks the integrity of the database, validating that every row of the tables of the set
+    /// decodes with its registered decoder.
+    pub fn check(&self) -> Result<CheckReport, DatabaseError> {
+        let registry = self.0.table_registry();
+        let options = CheckOptions::default().with_validator(|table, key, value| {
+            match registry.format_row(table, key, value) {
+                Some(Err(err)) => Err(err.to_string()),
+                _ => Ok(()),
+            }
+        });
+
+        self.0
+            .check(&options)
+            .map_err(|e| DatabaseError::Read(e.into()))
+    }
+
+    /// Lists the slots of the reader lock table, with the lag of the snapshot each one holds.
+    pub fn readers(&self) -> Result<Vec<ReaderInfo>, DatabaseError> {
+        self.0.readers().map_err(|e| DatabaseError::Read(e.into()))
+    }
+
+    /// Clears the reader slots held by dead processes, returning the number of cleared slots.
+    pub fn reader_check(&self) -> Result<usize, DatabaseError> {
+        self.0
+            .reader_check()
+            .map_err(|e| DatabaseError::Other(format!("reader check failed: {e}")))
+    }
+
+    /// Returns the number of times a slow reader kept the database from reusing pages, see
+    /// [`SlowReaderPolicy`](crate::SlowReaderPolicy).
+    pub fn slow_reader_events(&self) -> u64 {
+        self.0.slow_reader_events()
+    }
+
+    /// Returns the number of reader slots cleared by the slow reader policy.
+    pub fn cleared_slow_readers(&self) -> u64 {
+        self.0.cleared_slow_readers()
+    }
+
     /// Takes a function and passes a RW transaction
     /// makes sure it's committed at the end of execution
     pub fn write<F, R>(&self, f: F) -> Result<R, DatabaseError>
@@ -92,6 +212,34 @@ impl<S: TableSet> LibmdbxProvider<S> {
         Ok(res)
     }
 
+    /// Same as [`LibmdbxProvider::write`], failing immediately with a
+    /// [`DatabaseError::InitTx`] of code `MDBX_BUSY` if another write transaction is open on the
+    /// database, in this process or another one.
+    pub fn try_write<F, R>(&self, f: F) -> Result<R, DatabaseError>
+    where
+        F: FnOnce(&LibmdbxTx<RW>) -> R,
+    {
+        let tx = LibmdbxTx::try_new_rw_tx(&self.0)?;
+        let res = f(&tx);
+        tx.commit()?;
+
+        Ok(res)
+    }
+
+    /// Same as [`LibmdbxProvider::write`], giving up with a [`DatabaseError::InitTx`] of code
+    /// `MDBX_BUSY` if the write lock can't be taken before `deadline`.
+    pub fn write_with_deadline<F, R>(&self, deadline: Instant, f: F) -> Result<R, DatabaseError>
+    where
+        F: FnOnce(&LibmdbxTx<RW>) -> R,
+    {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let tx = LibmdbxTx::new_rw_tx_timeout(&self.0, timeout)?;
+        let res = f(&tx);
+        tx.commit()?;
+
+        Ok(res)
+    }
+
     /// Takes an async function and passes a RW transaction
     /// makes sure it's committed at the end of execution
     pub async fn write_async<F, R>(&self, f: F) -> Result<R, DatabaseError>
@@ -105,28 +253,97 @@ impl<S: TableSet> LibmdbxProvider<S> {
         Ok(res)
     }
 
+    /// Takes a function and passes a RO transaction, committed at the end of execution or parked
+    /// for reuse when the environment has a read transaction pool with room left, see
+    /// [`DatabaseArguments::with_read_txn_pool_size`].
     pub fn read<F, R>(&self, f: F) -> Result<R, DatabaseError>
     where
         F: FnOnce(&LibmdbxTx<RO>) -> R,
     {
         let tx = self.ro_tx()?;
         let res = f(&tx);
-        tx.commit()?;
+        tx.release(&self.0)?;
 
         Ok(res)
     }
 
+    /// Takes an async function and passes a RO transaction, see [`LibmdbxProvider::read`].
     pub async fn read_async<F, R>(&self, f: F) -> Result<R, DatabaseError>
     where
         F: AsyncFnOnce(&LibmdbxTx<RO>) -> R,
     {
         let tx = self.ro_tx()?;
         let res = f(&tx).await;
-        tx.commit()?;
+        tx.release(&self.0)?;
 
         Ok(res)
     }
 
+    /// Returns the canary of the last committed write transaction, usable as a checkpoint stored
+    /// atomically with the rows written along with [`LibmdbxTx::put_canary`]. Reading it doesn't
+    /// open any table.
+    pub fn checkpoint(&self) -> Result<Canary, DatabaseError> {
+        self.0.canary().map_err(|e| DatabaseError::Read(e.into()))
+    }
+
+    /// Scans `T` on up to `partitions` threads, each walking a range of keys holding roughly the
+    /// same number of rows within its own read transaction.
+    ///
+    /// Returns what `f` returned for every range, in key order. To stream the rows instead, `f` can
+    /// send them through a channel. The ranges are read from different transactions, so the scan
+    /// isn't a consistent snapshot of a table written concurrently.
+    pub fn par_scan<T, F, R>(&self, partitions: usize, f: F) -> Result<Vec<R>, DatabaseError>
+    where
+        T: Table,
+        F: Fn(RangeWalker<'_, T, LibmdbxCursor<T, RO>>) -> R + Sync,
+        R: Send,
+    {
+        let mut starts = vec![Bound::Unbounded];
+        for key in self.read(|tx| tx.split_keys::<T>(partitions))?? {
+            starts.push(Bound::Included(T::Key::decode(&key)?));
+        }
+
+        let mut ranges = Vec::with_capacity(starts.len());
+        let mut ends = starts.iter().skip(1);
+        for start in &starts {
+            let end = match ends.next() {
+                Some(Bound::Included(key)) => Bound::Excluded(key.clone()),
+                _ => Bound::Unbounded,
+            };
+            ranges.push((start.clone(), end));
+        }
+
+        std::thread::scope(|scope| {
+            let f = &f;
+            let workers: Vec<_> = ranges
+                .into_iter()
+                .map(|range| {
+                    scope.spawn(move || {
+                        self.read(|tx| {
+                            let mut cursor = tx.new_cursor::<T>()?;
+                            Ok::<_, DatabaseError>(f(cursor.walk_range(range)?))
+                        })?
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        })
+    }
+
+    /// Takes a [`Snapshot`] of the database, which can be shared across threads and stays open
+    /// until dropped.
+    pub fn snapshot(&self) -> Result<Snapshot, DatabaseError> {
+        Snapshot::new(&self.0)
+    }
+
     /// returns a RO transaction
     fn ro_tx(&self) -> Result<LibmdbxTx<RO>, DatabaseError> {
         let tx = LibmdbxTx::new_ro_tx(&self.0)?;
@@ -141,3 +358,215 @@ impl<S: TableSet> LibmdbxProvider<S> {
         Ok(tx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use reth_db::transaction::DbTxMut;
+
+    use super::*;
+    use crate::{IntegerKey, test_utils};
+
+    db_table!((Rows) | u64, IntegerKey<u64>);
+    db_table!((Counters) | u64, IntegerKey<u64>);
+
+    tables!(ScanTables, 2, [Rows, Counters]);
+
+    /// Scans `Rows` on `partitions` threads, returning the keys of every range.
+    fn scan(db: &LibmdbxProvider<ScanTables>, partitions: usize) -> Vec<Vec<u64>> {
+        db.par_scan::<Rows, _, _>(partitions, |walker| {
+            walker.map(|row| row.unwrap().0).collect()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn par_scan_returns_every_row_once() {
+        let (_dir, db) = test_utils::provider::<ScanTables>();
+        assert_eq!(scan(&db, 4), vec![Vec::<u64>::new()]);
+
+        db.write(|tx| {
+            for key in 0..1000 {
+                tx.put::<Rows>(key * 3, IntegerKey(key))?;
+            }
+            Ok::<_, DatabaseError>(())
+        })
+        .unwrap()
+        .unwrap();
+
+        let keys: Vec<u64> = (0..1000).map(|key| key * 3).collect();
+        for partitions in [1, 2, 7, 16] {
+            let ranges = scan(&db, partitions);
+            assert!(ranges.len() <= partitions, "{partitions} partitions");
+            assert!(ranges.iter().all(|range| !range.is_empty()));
+            assert_eq!(ranges.concat(), keys, "{partitions} partitions");
+        }
+        assert!(scan(&db, 16).len() > 1);
+
+        // More partitions than rows scan the table as a whole.
+        assert_eq!(scan(&db, 5000), vec![keys]);
+    }
+
+    #[test]
+    fn try_write_gives_up_while_another_write_is_open() {
+        let (_dir, db) = test_utils::provider::<ScanTables>();
+        let is_busy = |result: Result<(), DatabaseError>| match result {
+            Err(DatabaseError::InitTx(info)) => {
+                info.code == libmdbx_native::Error::Busy.to_err_code()
+            }
+            _ => false,
+        };
+
+        let (locked, wait_locked) = mpsc::channel();
+        let (release, wait_release) = mpsc::channel::<()>();
+        thread::scope(|scope| {
+            let db = &db;
+            let writer = scope.spawn(move || {
+                db.write(|tx| {
+                    locked.send(()).unwrap();
+                    wait_release.recv().unwrap();
+                    tx.put::<Rows>(1, IntegerKey(1))
+                })
+            });
+            wait_locked.recv().unwrap();
+
+            assert!(is_busy(db.try_write(|_| ())));
+            let timeout = Duration::from_millis(100);
+            let start = Instant::now();
+            assert!(is_busy(db.write_with_deadline(start + timeout, |_| ())));
+            assert!(start.elapsed() >= timeout);
+
+            // The deadline variant waits for the lock to be released.
+            release.send(()).unwrap();
+            let deadline = Instant::now() + Duration::from_secs(10);
+            db.write_with_deadline(deadline, |tx| tx.put::<Rows>(2, IntegerKey(2)))
+                .unwrap()
+                .unwrap();
+            writer.join().unwrap().unwrap().unwrap();
+        });
+
+        db.try_write(|tx| tx.put::<Rows>(3, IntegerKey(3)))
+            .unwrap()
+            .unwrap();
+        db.read(|tx| {
+            for key in 1..=3 {
+                assert_eq!(tx.get::<Rows>(key).unwrap(), Some(IntegerKey(key)));
+            }
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn sequences_are_transactional_and_per_table() {
+        let (_dir, db) = test_utils::provider::<ScanTables>();
+
+        db.write(|tx| {
+            assert_eq!(tx.current_sequence::<Rows>()?, 0);
+            assert_eq!(tx.next_sequence::<Rows>(1)?, 0);
+            assert_eq!(tx.next_sequence::<Rows>(1)?, 1);
+            assert_eq!(tx.next_sequence::<Rows>(5)?, 2);
+            assert_eq!(tx.current_sequence::<Rows>()?, 7);
+            assert_eq!(tx.next_sequence::<Counters>(1)?, 0);
+            Ok::<_, DatabaseError>(())
+        })
+        .unwrap()
+        .unwrap();
+
+        let sequences = || {
+            db.read(|tx| {
+                (
+                    tx.current_sequence::<Rows>().unwrap(),
+                    tx.current_sequence::<Counters>().unwrap(),
+                )
+            })
+            .unwrap()
+        };
+        assert_eq!(sequences(), (7, 1));
+
+        // An aborted transaction rolls its increments back.
+        let tx = db.rw_tx().unwrap();
+        assert_eq!(tx.next_sequence::<Rows>(10).unwrap(), 7);
+        assert_eq!(tx.next_sequence::<Counters>(10).unwrap(), 1);
+        tx.abort();
+        assert_eq!(sequences(), (7, 1));
+
+        db.write(|tx| tx.next_sequence::<Counters>(1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(sequences(), (7, 2));
+    }
+
+    #[test]
+    fn canary_is_committed_with_the_transaction() {
+        let (_dir, db) = test_utils::provider::<ScanTables>();
+        assert_eq!(db.checkpoint().unwrap(), Canary::default());
+
+        let canary = Canary {
+            x: 1,
+            y: 2,
+            z: 3,
+            v: 0,
+        };
+        let xyz = |canary: Canary| (canary.x, canary.y, canary.z);
+        db.write(|tx| {
+            tx.put::<Rows>(1, IntegerKey(1))?;
+            tx.put_canary(canary)?;
+            assert_eq!(xyz(tx.canary()?), (1, 2, 3));
+            Ok::<_, DatabaseError>(())
+        })
+        .unwrap()
+        .unwrap();
+
+        let checkpoint = db.checkpoint().unwrap();
+        assert_eq!(xyz(checkpoint), (1, 2, 3));
+        assert!(checkpoint.v > 0);
+        assert_eq!(db.read(|tx| tx.canary()).unwrap().unwrap(), checkpoint);
+
+        // An aborted canary is dropped, and later writes keep the committed one.
+        let tx = db.rw_tx().unwrap();
+        tx.put_canary(Canary { x: 4, ..canary }).unwrap();
+        tx.abort();
+        db.write(|tx| tx.put::<Rows>(2, IntegerKey(2)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(db.checkpoint().unwrap(), checkpoint);
+        assert_eq!(db.read(|tx| tx.canary()).unwrap().unwrap(), checkpoint);
+    }
+
+    #[test]
+    fn delete_range_commits_every_chunk() {
+        let (_dir, db) = test_utils::provider::<ScanTables>();
+        db.write(|tx| {
+            for key in 0..1000 {
+                tx.put::<Rows>(key, IntegerKey(key))?;
+            }
+            Ok::<_, DatabaseError>(())
+        })
+        .unwrap()
+        .unwrap();
+        let last_commit = || db.read(|tx| tx.inner.id().unwrap()).unwrap();
+
+        // 9 full chunks of 64 rows and a last one of 25.
+        let before = last_commit();
+        assert_eq!(db.delete_range::<Rows>(100..=700, 64).unwrap(), 601);
+        assert_eq!(last_commit() - before, 10);
+
+        let excluded = (Bound::Excluded(800), Bound::Excluded(900));
+        assert_eq!(db.delete_range::<Rows>(excluded, 10).unwrap(), 99);
+        assert_eq!(db.delete_range::<Rows>(100..=700, 64).unwrap(), 0);
+
+        let keys: Vec<u64> = db
+            .read(|tx| {
+                tx.cursor_read::<Rows>()
+                    .unwrap()
+                    .walk(None)
+                    .unwrap()
+                    .map(|row| row.unwrap().0)
+                    .collect()
+            })
+            .unwrap();
+        let expected: Vec<u64> = (0..100).chain(701..=800).chain(900..1000).collect();
+        assert_eq!(keys, expected);
+    }
+}
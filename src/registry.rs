@@ -0,0 +1,305 @@
+use std::{collections::HashMap, time::Duration};
+
+use libmdbx_native::{RW, WriteFlags};
+use reth_db::{
+    DatabaseError, DatabaseWriteOperation,
+    table::{Decode, Decompress, Table},
+};
+use reth_storage_errors::db::DatabaseWriteError;
+
+use crate::{
+    error::Operation,
+    implementation::{
+        DynDecoder, LibmdbxTx,
+        dyn_table::{debug_decoder, format_with},
+        utils::{compress_value, encode_key},
+    },
+    traits::SecondaryIndex,
+};
+
+/// Updates an index table after a row of its primary table changed.
+///
+/// Takes the encoded primary key, the previous compressed value (if any) and the new compressed
+/// value (if any).
+pub(crate) type IndexHook =
+    fn(&LibmdbxTx<RW>, &[u8], Option<&[u8]>, Option<&[u8]>) -> Result<(), DatabaseError>;
+
+/// A secondary index registered for a primary table.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IndexEntry {
+    /// Name of the index table.
+    pub(crate) table: &'static str,
+    /// Keeps the index table in sync with the primary table.
+    pub(crate) maintain: IndexHook,
+}
+
+/// Per-table behaviour that has to be applied by the transactions, collected from a
+/// [`TableSet`](crate::TableSet) when the database is opened.
+#[derive(Debug, Default)]
+pub struct TableRegistry {
+    /// Secondary indexes keyed by the name of the primary table they index.
+    indexes: HashMap<&'static str, Vec<IndexEntry>>,
+    /// Time-to-live of the rows of expiring tables, keyed by table name.
+    expiring: HashMap<&'static str, Duration>,
+    /// Decoders used to display the rows of [`DynTable`](crate::DynTable)s, keyed by table name.
+    decoders: HashMap<&'static str, DynDecoder>,
+}
+
+impl TableRegistry {
+    /// Registers `I` so that it is maintained on every `put`/`delete` of its primary table.
+    pub fn secondary_index<I>(&mut self) -> &mut Self
+    where
+        I: SecondaryIndex,
+        I::Value: From<<I::Primary as Table>::Key>,
+    {
+        self.indexes
+            .entry(<I::Primary as Table>::NAME)
+            .or_default()
+            .push(IndexEntry {
+                table: I::NAME,
+                maintain: maintain_index::<I>,
+            });
+        self
+    }
+
+    /// Returns the secondary indexes of the given primary table.
+    pub(crate) fn indexes_of(&self, table: &str) -> &[IndexEntry] {
+        self.indexes
+            .get(table)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Registers `T` as an expiring table whose rows are deleted `ttl` after they were written.
+    ///
+    /// Expired rows are hidden from reads right away and deleted by the reaper started with
+    /// [`LibmdbxProvider::start_reaper`](crate::LibmdbxProvider::start_reaper).
+    pub fn expiring<T: Table>(&mut self, ttl: Duration) -> &mut Self {
+        self.expiring.insert(T::NAME, ttl);
+        self
+    }
+
+    /// Returns the time-to-live of the rows of the given table if it is an expiring table.
+    pub fn ttl_of(&self, table: &str) -> Option<Duration> {
+        self.expiring.get(table).copied()
+    }
+
+    /// Registers a decoder formatting the rows of `T` with their [`Debug`](std::fmt::Debug)
+    /// representation.
+    pub fn decoder<T: Table>(&mut self) -> &mut Self {
+        self.raw_decoder(T::NAME, debug_decoder::<T>)
+    }
+
+    /// Registers a custom decoder for the rows of the table `name`.
+    pub fn raw_decoder(&mut self, name: &'static str, decoder: DynDecoder) -> &mut Self {
+        self.decoders.insert(name, decoder);
+        self
+    }
+
+    /// Returns the decoder registered for the given table.
+    pub fn decoder_of(&self, table: &str) -> Option<DynDecoder> {
+        self.decoders.get(table).copied()
+    }
+
+    /// Formats a raw row of `table` with its registered decoder, or returns [None] if there is
+    /// none.
+    pub fn format_row(
+        &self,
+        table: &str,
+        key: &[u8],
+        value: &[u8],
+    ) -> Option<Result<String, DatabaseError>> {
+        let decoder = self.decoder_of(table)?;
+        Some(format_with(
+            decoder,
+            self.ttl_of(table).is_some(),
+            key,
+            value,
+        ))
+    }
+
+    /// Returns `true` if at least one expiring table was registered.
+    pub(crate) fn has_expiring_tables(&self) -> bool {
+        !self.expiring.is_empty()
+    }
+}
+
+/// Moves the primary key from the index entry extracted from the old value to the one extracted
+/// from the new value.
+fn maintain_index<I>(
+    tx: &LibmdbxTx<RW>,
+    key: &[u8],
+    old: Option<&[u8]>,
+    new: Option<&[u8]>,
+) -> Result<(), DatabaseError>
+where
+    I: SecondaryIndex,
+    I::Value: From<<I::Primary as Table>::Key>,
+{
+    let primary_key = <<I::Primary as Table>::Key as Decode>::decode(key)?;
+    let extract = |value: Option<&[u8]>| -> Result<Option<I::Key>, DatabaseError> {
+        match value {
+            Some(value) => Ok(I::extract(&primary_key, &Decompress::decompress(value)?)),
+            None => Ok(None),
+        }
+    };
+
+    let (old, new) = (extract(old)?, extract(new)?);
+    if old == new {
+        return Ok(());
+    }
+
+    let dbi = tx.get_dbi::<I>()?;
+    let entry: I::Value = primary_key.clone().into();
+
+    // The duplicates of a DUPSORT primary row share its key, so the entry stays while another one
+    // is still indexed under the old index key.
+    if let Some(old) = old {
+        let shared =
+            <I::Primary as Table>::DUPSORT && !tx.indexed_dups::<I>(&primary_key, &old)?.is_empty();
+        if !shared {
+            let key = encode_key::<I>(Operation::Delete, old)?;
+            let entry = compress_value::<I>(Operation::Delete, &key, &entry)?;
+            tx.inner
+                .del(dbi, &key, Some(entry.as_slice()))
+                .map_err(|e| DatabaseError::Delete(e.into()))?;
+        }
+    }
+
+    if let Some(new) = new {
+        let operation = DatabaseWriteOperation::Put;
+        let key = encode_key::<I>(operation, new)?;
+        let entry = compress_value::<I>(operation, &key, &entry)?;
+        tx.inner
+            .put(dbi, &key, &entry, WriteFlags::UPSERT)
+            .map_err(|e| {
+                DatabaseError::from(DatabaseWriteError {
+                    info: e.into(),
+                    operation,
+                    table_name: I::NAME,
+                    key,
+                })
+            })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use reth_db::transaction::{DbTx, DbTxMut};
+
+    use crate::{IntegerKey, SecondaryIndex, test_utils};
+
+    db_table!((Owners) | u64, IntegerKey<u64>);
+    db_table!((OwnersByValue, index_of = Owners) | IntegerKey<u64>);
+
+    impl SecondaryIndex for OwnersByValue {
+        type Primary = Owners;
+
+        fn extract(_: &u64, value: &IntegerKey<u64>) -> Option<IntegerKey<u64>> {
+            Some(*value)
+        }
+    }
+
+    db_table!((Balances, integer_key, integer_dup) | u64 => u64);
+    db_table!((BalancesByParity, index_of = Balances) | IntegerKey<u64>);
+
+    impl SecondaryIndex for BalancesByParity {
+        type Primary = Balances;
+
+        fn extract(_: &IntegerKey<u64>, value: &IntegerKey<u64>) -> Option<IntegerKey<u64>> {
+            Some(IntegerKey(value.0 % 2))
+        }
+    }
+
+    tables!(
+        IndexedTables,
+        4,
+        [Owners, OwnersByValue, Balances, BalancesByParity]
+    );
+
+    #[test]
+    fn secondary_index_follows_puts_and_deletes() {
+        let (_dir, db) = test_utils::provider::<IndexedTables>();
+        let by_value = |value: u64| {
+            db.read(|tx| tx.get_by_index::<OwnersByValue>(IntegerKey(value)))
+                .unwrap()
+                .unwrap()
+        };
+
+        db.write(|tx| {
+            tx.put::<Owners>(1, IntegerKey(7))?;
+            tx.put::<Owners>(2, IntegerKey(7))?;
+            tx.put::<Owners>(3, IntegerKey(8))
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(by_value(7), [(1, IntegerKey(7)), (2, IntegerKey(7))]);
+        assert_eq!(by_value(8), [(3, IntegerKey(8))]);
+
+        db.write(|tx| tx.put::<Owners>(2, IntegerKey(8)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_value(7), [(1, IntegerKey(7))]);
+        assert_eq!(by_value(8), [(2, IntegerKey(8)), (3, IntegerKey(8))]);
+
+        assert!(
+            db.write(|tx| tx.delete::<Owners>(1, None))
+                .unwrap()
+                .unwrap()
+        );
+        assert!(by_value(7).is_empty());
+        assert_eq!(
+            db.read(|tx| tx.entries::<OwnersByValue>())
+                .unwrap()
+                .unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn secondary_index_of_dupsort_table_keeps_shared_entries() {
+        let (_dir, db) = test_utils::provider::<IndexedTables>();
+        let by_parity = |parity: u64| {
+            db.read(|tx| tx.get_by_index::<BalancesByParity>(IntegerKey(parity)))
+                .unwrap()
+                .unwrap()
+        };
+        let row = |key: u64, value: u64| (IntegerKey(key), IntegerKey(value));
+
+        db.write(|tx| {
+            for value in [3, 4, 5] {
+                tx.put::<Balances>(IntegerKey(1), IntegerKey(value))?;
+            }
+            Ok::<_, reth_db::DatabaseError>(())
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(by_parity(1), [row(1, 3), row(1, 5)]);
+        assert_eq!(by_parity(0), [row(1, 4)]);
+
+        // Another odd duplicate is left, the index entry must stay.
+        db.write(|tx| tx.delete::<Balances>(IntegerKey(1), Some(IntegerKey(3))))
+            .unwrap()
+            .unwrap();
+        assert_eq!(by_parity(1), [row(1, 5)]);
+
+        db.write(|tx| tx.delete::<Balances>(IntegerKey(1), Some(IntegerKey(5))))
+            .unwrap()
+            .unwrap();
+        assert!(by_parity(1).is_empty());
+        assert_eq!(by_parity(0), [row(1, 4)]);
+
+        db.write(|tx| tx.delete::<Balances>(IntegerKey(1), None))
+            .unwrap()
+            .unwrap();
+        assert!(by_parity(0).is_empty());
+        assert_eq!(
+            db.read(|tx| tx.entries::<BalancesByParity>())
+                .unwrap()
+                .unwrap(),
+            0
+        );
+    }
+}
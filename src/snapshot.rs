@@ -0,0 +1,208 @@
+//! Long-lived read transactions, see [`LibmdbxProvider::snapshot`](crate::LibmdbxProvider::snapshot).
+
+use std::{
+    ops::Deref,
+    time::{Duration, Instant},
+};
+
+use libmdbx_native::RO;
+use reth_db::{
+    DatabaseError,
+    table::{DupSort, Encode, Table},
+    transaction::DbTx,
+};
+
+use crate::{
+    error::{self, ErrorContext, Operation},
+    implementation::{DatabaseEnv, LibmdbxCursor, LibmdbxTx},
+};
+
+/// A consistent read-only view of the database, kept open until dropped.
+///
+/// The snapshot is `Send + Sync`, so worker threads can share it by reference to read the same
+/// state of the database. Holding it keeps the database from reusing the pages freed since it was
+/// taken, and it's subject to the read transaction timeout unless
+/// [`Snapshot::disable_timeout`] is called.
+#[derive(Debug)]
+pub struct Snapshot {
+    tx: LibmdbxTx<RO>,
+    /// Id of the transaction, which is the id of the last write transaction seen.
+    id: u64,
+    opened: Instant,
+}
+
+impl Snapshot {
+    pub(crate) fn new(env: &DatabaseEnv) -> Result<Self, DatabaseError> {
+        let tx = LibmdbxTx::new_ro_tx(env)?;
+        let id = tx
+            .inner
+            .id()
+            .map_err(|e| error::Error::Mdbx(e, ErrorContext::new(Operation::BeginTx)))?;
+
+        Ok(Self {
+            tx,
+            id,
+            opened: Instant::now(),
+        })
+    }
+
+    /// Returns the id of the transaction the snapshot reads from.
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the time elapsed since the snapshot was taken.
+    pub fn age(&self) -> Duration {
+        self.opened.elapsed()
+    }
+
+    /// Keeps the snapshot open past the maximum read transaction duration.
+    pub fn disable_timeout(&self) {
+        self.tx.inner.disable_timeout();
+    }
+}
+
+impl Deref for Snapshot {
+    type Target = LibmdbxTx<RO>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+impl DbTx for Snapshot {
+    type Cursor<T: Table> = LibmdbxCursor<T, RO>;
+    type DupCursor<T: DupSort> = LibmdbxCursor<T, RO>;
+
+    fn get<T: Table>(&self, key: T::Key) -> Result<Option<T::Value>, DatabaseError> {
+        self.tx.get::<T>(key)
+    }
+
+    fn get_by_encoded_key<T: Table>(
+        &self,
+        key: &<T::Key as Encode>::Encoded,
+    ) -> Result<Option<T::Value>, DatabaseError> {
+        self.tx.get_by_encoded_key::<T>(key)
+    }
+
+    fn commit(self) -> Result<bool, DatabaseError> {
+        self.tx.commit()
+    }
+
+    fn abort(self) {
+        self.tx.abort()
+    }
+
+    fn cursor_read<T: Table>(&self) -> Result<Self::Cursor<T>, DatabaseError> {
+        self.tx.cursor_read::<T>()
+    }
+
+    fn cursor_dup_read<T: DupSort>(&self) -> Result<Self::DupCursor<T>, DatabaseError> {
+        self.tx.cursor_dup_read::<T>()
+    }
+
+    fn entries<T: Table>(&self) -> Result<usize, DatabaseError> {
+        self.tx.entries::<T>()
+    }
+
+    fn disable_long_read_transaction_safety(&mut self) {
+        self.disable_timeout();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Barrier, thread};
+
+    use libmdbx_native::MaxReadTransactionDuration;
+    use reth_db::{ClientVersion, transaction::DbTxMut};
+
+    use super::*;
+    use crate::{DatabaseArguments, IntegerKey, test_utils};
+
+    const MAX_DURATION: Duration = Duration::from_millis(100);
+
+    db_table!((Rows) | u64, IntegerKey<u64>);
+
+    tables!(SnapshotTables, 1, [Rows]);
+
+    const fn assert_send_sync<T: Send + Sync>() {}
+    const _: () = assert_send_sync::<Snapshot>();
+
+    #[test]
+    fn threads_read_the_state_the_snapshot_was_taken_at() {
+        let (_dir, db) = test_utils::provider::<SnapshotTables>();
+        db.write(|tx| tx.put::<Rows>(1, IntegerKey(1)))
+            .unwrap()
+            .unwrap();
+
+        let snapshot = db.snapshot().unwrap();
+        let id = snapshot.id();
+        let readers = 4;
+        // Waited on before and after the writer commits.
+        let barrier = Barrier::new(readers + 1);
+        thread::scope(|scope| {
+            let readers: Vec<_> = (0..readers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let before = snapshot.get::<Rows>(1).unwrap();
+                        barrier.wait();
+                        barrier.wait();
+                        let after = (
+                            snapshot.get::<Rows>(1).unwrap(),
+                            snapshot.get::<Rows>(2).unwrap(),
+                        );
+                        (before, after)
+                    })
+                })
+                .collect();
+
+            barrier.wait();
+            db.write(|tx| {
+                tx.put::<Rows>(1, IntegerKey(10))?;
+                tx.put::<Rows>(2, IntegerKey(2))
+            })
+            .unwrap()
+            .unwrap();
+            barrier.wait();
+
+            for reader in readers {
+                assert_eq!(
+                    reader.join().unwrap(),
+                    (Some(IntegerKey(1)), (Some(IntegerKey(1)), None))
+                );
+            }
+        });
+
+        assert_eq!(snapshot.id(), id);
+        let latest = db.snapshot().unwrap();
+        assert!(latest.id() > id);
+        assert_eq!(latest.get::<Rows>(1).unwrap(), Some(IntegerKey(10)));
+        assert!(snapshot.age() > latest.age());
+    }
+
+    #[test]
+    fn disabled_timeout_keeps_the_snapshot_open() {
+        let args = DatabaseArguments::new(ClientVersion::default())
+            .with_log_level(None)
+            .with_max_read_transaction_duration(Some(MaxReadTransactionDuration::Set(
+                MAX_DURATION,
+            )));
+        let (_dir, db) = test_utils::provider_with_args::<SnapshotTables>(args);
+        db.write(|tx| tx.put::<Rows>(1, IntegerKey(1)))
+            .unwrap()
+            .unwrap();
+
+        let kept = db.snapshot().unwrap();
+        kept.disable_timeout();
+        let timed_out = db.snapshot().unwrap();
+        thread::sleep(MAX_DURATION * 10);
+
+        assert!(kept.age() >= MAX_DURATION * 10);
+        assert_eq!(kept.get::<Rows>(1).unwrap(), Some(IntegerKey(1)));
+        assert_eq!(
+            timed_out.inner.id().err(),
+            Some(libmdbx_native::Error::ReadTransactionTimeout)
+        );
+    }
+}
@@ -27,6 +27,7 @@ macro_rules! tables {
     };
 
     (PRIVATE | $set_name:ident, $num_tables:expr, [$($table:ident),*]) => {
+        #[allow(dead_code)]
         impl $set_name {
             /// Array of all tables in database
             pub const ALL: [$set_name; $num_tables] = [$($set_name::$table,)*];
@@ -96,6 +97,12 @@ macro_rules! tables {
                 Ok(())
             }
 
+            fn register(registry: &mut libmdbx_bindings::TableRegistry) {
+                $(
+                    <$table as libmdbx_bindings::TableDet>::register(registry);
+                )*
+            }
+
             fn as_usize(&self) -> usize {
                 *self as usize
             }
@@ -105,6 +112,177 @@ macro_rules! tables {
 
 #[macro_export]
 macro_rules! db_table {
+    ( ( $table:ident, index_of = $primary:ty ) | $key:ty) => {
+        #[doc = concat!("Indexes [`", stringify!($primary), "`] by [`", stringify!($key), "`].")]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $table;
+
+        impl libmdbx_bindings::Table for $table {
+            type Key = $key;
+            type Value = <$primary as libmdbx_bindings::Table>::Key;
+
+            const NAME: &'static str = stringify!($table);
+            const DUPSORT: bool = true;
+        }
+
+        impl libmdbx_bindings::DupSort for $table {
+            type SubKey = <$primary as libmdbx_bindings::Table>::Key;
+        }
+
+        impl std::fmt::Display for $table {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", stringify!($table))
+            }
+        }
+
+        impl libmdbx_bindings::TableDet for $table {
+            fn table_type(&self) -> libmdbx_bindings::TableType {
+                libmdbx_bindings::TableType::DupSort
+            }
+
+            fn register(registry: &mut libmdbx_bindings::TableRegistry) {
+                registry.secondary_index::<Self>().decoder::<Self>();
+            }
+        }
+    };
+
+    ( ( $table:ident, integer_key, integer_dup ) | $key:ty => $value:ty) => {
+        #[doc = concat!("Takes a native-endian [`", stringify!($key), "`] as a key and returns native-endian [`", stringify!($value), "`] duplicates.")]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $table;
+
+        impl libmdbx_bindings::Table for $table {
+            type Key = libmdbx_bindings::IntegerKey<$key>;
+            type Value = libmdbx_bindings::IntegerKey<$value>;
+
+            const NAME: &'static str = stringify!($table);
+            const DUPSORT: bool = true;
+        }
+
+        impl libmdbx_bindings::DupSort for $table {
+            type SubKey = libmdbx_bindings::IntegerKey<$value>;
+        }
+
+        impl std::fmt::Display for $table {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", stringify!($table))
+            }
+        }
+
+        impl libmdbx_bindings::TableDet for $table {
+            fn table_type(&self) -> libmdbx_bindings::TableType {
+                libmdbx_bindings::TableType::DupSort
+            }
+
+            fn database_flags(&self) -> libmdbx_bindings::DatabaseFlags {
+                libmdbx_bindings::DatabaseFlags::DUP_SORT
+                    | libmdbx_bindings::DatabaseFlags::DUP_FIXED
+                    | libmdbx_bindings::DatabaseFlags::INTEGER_KEY
+                    | libmdbx_bindings::DatabaseFlags::INTEGER_DUP
+            }
+        }
+    };
+
+    ( ( $table:ident, integer_key ) | $key:ty, $value:ty) => {
+        #[doc = concat!("Takes a native-endian [`", stringify!($key), "`] as a key and returns [`", stringify!($value), "`].")]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $table;
+
+        impl libmdbx_bindings::Table for $table {
+            type Key = libmdbx_bindings::IntegerKey<$key>;
+            type Value = $value;
+
+            const NAME: &'static str = stringify!($table);
+            const DUPSORT: bool = false;
+        }
+
+        impl std::fmt::Display for $table {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", stringify!($table))
+            }
+        }
+
+        impl libmdbx_bindings::TableDet for $table {
+            fn table_type(&self) -> libmdbx_bindings::TableType {
+                libmdbx_bindings::TableType::Table
+            }
+
+            fn database_flags(&self) -> libmdbx_bindings::DatabaseFlags {
+                libmdbx_bindings::DatabaseFlags::INTEGER_KEY
+            }
+        }
+    };
+
+    // MDBX stores the duplicates of a `DUP_FIXED` table at the size of the first one, so every
+    // value has to compress to the same number of bytes. Only use it for fixed-width values such
+    // as integers or arrays, not for values compressed to a variable length.
+    ( ( $table:ident, dup_fixed ) | $key:ty => $subkey:ty, $value:ty) => {
+        #[doc = concat!("Takes [`", stringify!($key), "`] as a key and returns fixed-size [`", stringify!($value), "`] duplicates sorted by [`", stringify!($subkey), "`].")]
+        ///
+        /// Every value must compress to the same number of bytes, writing a value of another size
+        /// fails.
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $table;
+
+        impl libmdbx_bindings::Table for $table {
+            type Key = $key;
+            type Value = $value;
+
+            const NAME: &'static str = stringify!($table);
+            const DUPSORT: bool = true;
+        }
+
+        impl libmdbx_bindings::DupSort for $table {
+            type SubKey = $subkey;
+        }
+
+        impl std::fmt::Display for $table {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", stringify!($table))
+            }
+        }
+
+        impl libmdbx_bindings::TableDet for $table {
+            fn table_type(&self) -> libmdbx_bindings::TableType {
+                libmdbx_bindings::TableType::DupSort
+            }
+
+            fn database_flags(&self) -> libmdbx_bindings::DatabaseFlags {
+                libmdbx_bindings::DatabaseFlags::DUP_SORT | libmdbx_bindings::DatabaseFlags::DUP_FIXED
+            }
+        }
+    };
+
+    ( ( $table:ident, ttl = $ttl:expr ) | $key:ty, $value:ty) => {
+        #[doc = concat!("Takes [`", stringify!($key), "`] as a key and returns [`", stringify!($value), "`]. Rows expire after `", stringify!($ttl), "`.")]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $table;
+
+        impl libmdbx_bindings::Table for $table {
+            type Key = $key;
+            type Value = $value;
+
+            const NAME: &'static str = stringify!($table);
+            const DUPSORT: bool = false;
+        }
+
+        impl std::fmt::Display for $table {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", stringify!($table))
+            }
+        }
+
+        impl libmdbx_bindings::TableDet for $table {
+            fn table_type(&self) -> libmdbx_bindings::TableType {
+                libmdbx_bindings::TableType::Table
+            }
+
+            fn register(registry: &mut libmdbx_bindings::TableRegistry) {
+                registry.expiring::<Self>($ttl).decoder::<Self>();
+            }
+        }
+    };
+
     ( ( $table:ident ) | $key:ty, $value:ty) => {
         #[doc = concat!("Takes [`", stringify!($key), "`] as a key and returns [`", stringify!($value), "`].")]
         #[derive(Clone, Copy, Debug, Default)]
@@ -0,0 +1,21 @@
+//! Helpers shared by the unit tests.
+
+use reth_db::ClientVersion;
+use tempfile::TempDir;
+
+use crate::{DatabaseArguments, LibmdbxProvider, TableSet};
+
+/// Opens a provider of `S` in a new temporary directory, deleted when the returned guard drops.
+pub(crate) fn provider<S: TableSet>() -> (TempDir, LibmdbxProvider<S>) {
+    provider_with_args(DatabaseArguments::new(ClientVersion::default()).with_log_level(None))
+}
+
+/// Same as [`provider`], opening the environment with the given arguments.
+pub(crate) fn provider_with_args<S: TableSet>(
+    args: DatabaseArguments,
+) -> (TempDir, LibmdbxProvider<S>) {
+    let dir = tempfile::tempdir().unwrap();
+    let provider = LibmdbxProvider::init_db_with_args(dir.path(), args).unwrap();
+
+    (dir, provider)
+}
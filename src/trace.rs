@@ -0,0 +1,265 @@
+//! Structured spans and events of the table operations, see [`TraceLevel`].
+
+use reth_db::DatabaseError;
+#[cfg(feature = "trace-ops")]
+use std::sync::atomic::{AtomicU8, Ordering};
+#[cfg(feature = "trace-ops")]
+use tracing::{Span, debug_span, field::Empty, trace_span, warn};
+
+/// Whether an operation reads or writes the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Access {
+    Read,
+    Write,
+}
+
+/// What the table operations emit, on top of the filters of the `tracing` subscriber.
+///
+/// Spans are named `mdbx_op` under the `libmdbx_bindings::ops` target and carry the table, the
+/// operation, the encoded key length, the value size and the outcome. Write spans are at the
+/// `DEBUG` level and read spans at the `TRACE` level.
+#[cfg(feature = "trace-ops")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum TraceLevel {
+    /// Nothing is emitted.
+    Off = 0,
+    /// Failed operations emit a warning.
+    #[default]
+    Errors = 1,
+    /// Writes run in a span, failed operations emit a warning.
+    Writes = 2,
+    /// Reads and writes run in a span, failed operations emit a warning.
+    All = 3,
+}
+
+#[cfg(feature = "trace-ops")]
+static LEVEL: AtomicU8 = AtomicU8::new(TraceLevel::Errors as u8);
+
+/// Sets what the table operations of every environment emit from now on.
+#[cfg(feature = "trace-ops")]
+pub fn set_trace_level(level: TraceLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Returns what the table operations currently emit.
+#[cfg(feature = "trace-ops")]
+pub fn trace_level() -> TraceLevel {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => TraceLevel::Off,
+        1 => TraceLevel::Errors,
+        2 => TraceLevel::Writes,
+        _ => TraceLevel::All,
+    }
+}
+
+/// Runs `f` as `operation` on `table`, within a span and reporting its failure according to the
+/// [`TraceLevel`].
+#[cfg(feature = "trace-ops")]
+pub(crate) fn traced<R>(
+    table: &'static str,
+    operation: &'static str,
+    access: Access,
+    f: impl FnOnce() -> Result<R, DatabaseError>,
+) -> Result<R, DatabaseError> {
+    let level = trace_level();
+    if level == TraceLevel::Off {
+        return f();
+    }
+
+    let span = match (level, access) {
+        (TraceLevel::All, Access::Read) => trace_span!(
+            target: "libmdbx_bindings::ops", "mdbx_op",
+            table, operation, key_len = Empty, value_len = Empty, outcome = Empty
+        ),
+        (TraceLevel::Writes | TraceLevel::All, Access::Write) => debug_span!(
+            target: "libmdbx_bindings::ops", "mdbx_op",
+            table, operation, key_len = Empty, value_len = Empty, outcome = Empty
+        ),
+        _ => Span::none(),
+    };
+
+    let result = span.in_scope(f);
+    match &result {
+        Ok(_) => {
+            span.record("outcome", "ok");
+        }
+        Err(error) => {
+            span.record("outcome", "error");
+            warn!(target: "libmdbx_bindings::ops", table, operation, %error, "Database operation failed");
+        }
+    }
+
+    result
+}
+
+#[cfg(not(feature = "trace-ops"))]
+#[inline(always)]
+pub(crate) fn traced<R>(
+    _table: &'static str,
+    _operation: &'static str,
+    _access: Access,
+    f: impl FnOnce() -> Result<R, DatabaseError>,
+) -> Result<R, DatabaseError> {
+    f()
+}
+
+/// Records the encoded key length and the stored value size on the span of the current operation.
+#[inline]
+pub(crate) fn record_sizes(key_len: usize, value_len: Option<usize>) {
+    #[cfg(feature = "trace-ops")]
+    if trace_level() > TraceLevel::Errors {
+        let span = Span::current();
+        span.record("key_len", key_len);
+        if let Some(value_len) = value_len {
+            span.record("value_len", value_len);
+        }
+    }
+
+    #[cfg(not(feature = "trace-ops"))]
+    let _ = (key_len, value_len);
+}
+
+#[cfg(all(test, feature = "trace-ops"))]
+mod tests {
+    use std::{collections::HashMap, fmt, sync::Arc};
+
+    use parking_lot::Mutex;
+    use reth_db::{
+        cursor::DbCursorRW,
+        transaction::{DbTx, DbTxMut},
+    };
+    use tracing::{
+        Event, Id, Metadata, Subscriber,
+        field::{Field, Visit},
+        span::{Attributes, Current, Record},
+    };
+
+    use super::*;
+    use crate::{IntegerKey, test_utils};
+
+    db_table!((Rows) | u64, IntegerKey<u64>);
+
+    tables!(TraceTables, 1, [Rows]);
+
+    /// Values of the fields of a span or an event, by field name.
+    type Fields = HashMap<&'static str, String>;
+
+    struct FieldsVisitor<'a>(&'a mut Fields);
+
+    impl Visit for FieldsVisitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name(), value.to_owned());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.insert(field.name(), format!("{value:?}"));
+        }
+    }
+
+    /// What [`CaptureSubscriber`] saw.
+    #[derive(Default)]
+    struct Captured {
+        /// Spans by id minus one, with their metadata and fields.
+        spans: Vec<(&'static Metadata<'static>, Fields)>,
+        /// Spans entered and not exited yet.
+        entered: Vec<Id>,
+        events: Vec<Fields>,
+    }
+
+    /// Subscriber keeping the spans and events of the table operations.
+    #[derive(Clone, Default)]
+    struct CaptureSubscriber(Arc<Mutex<Captured>>);
+
+    impl Subscriber for CaptureSubscriber {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "libmdbx_bindings::ops"
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields::new();
+            span.record(&mut FieldsVisitor(&mut fields));
+
+            let mut captured = self.0.lock();
+            captured.spans.push((span.metadata(), fields));
+            Id::from_u64(captured.spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut captured = self.0.lock();
+            let fields = &mut captured.spans[span.into_u64() as usize - 1].1;
+            values.record(&mut FieldsVisitor(fields));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields::new();
+            event.record(&mut FieldsVisitor(&mut fields));
+            self.0.lock().events.push(fields);
+        }
+
+        fn enter(&self, span: &Id) {
+            self.0.lock().entered.push(span.clone());
+        }
+
+        fn exit(&self, _: &Id) {
+            self.0.lock().entered.pop();
+        }
+
+        fn current_span(&self) -> Current {
+            let captured = self.0.lock();
+            match captured.entered.last() {
+                Some(id) => Current::new(id.clone(), captured.spans[id.into_u64() as usize - 1].0),
+                None => Current::none(),
+            }
+        }
+    }
+
+    #[test]
+    fn operations_run_in_spans_naming_the_table() {
+        let (_dir, db) = test_utils::provider::<TraceTables>();
+        let subscriber = CaptureSubscriber::default();
+
+        set_trace_level(TraceLevel::All);
+        tracing::subscriber::with_default(subscriber.clone(), || {
+            db.write(|tx| tx.put::<Rows>(1, IntegerKey(1)))
+                .unwrap()
+                .unwrap();
+            db.read(|tx| tx.get::<Rows>(1)).unwrap().unwrap();
+            // Appending before the last key fails.
+            db.write(|tx| tx.cursor_write::<Rows>()?.append(0, &IntegerKey(0)))
+                .unwrap()
+                .unwrap_err();
+        });
+        set_trace_level(TraceLevel::Errors);
+
+        let captured = subscriber.0.lock();
+        let spans: Vec<_> = captured
+            .spans
+            .iter()
+            .map(|(metadata, fields)| {
+                assert_eq!(metadata.name(), "mdbx_op");
+                assert_eq!(fields["table"], "Rows");
+                (
+                    *metadata.level(),
+                    fields["operation"].as_str(),
+                    fields["key_len"].as_str(),
+                    fields["outcome"].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (tracing::Level::DEBUG, "put", "8", "ok"),
+                (tracing::Level::TRACE, "get", "8", "ok"),
+                (tracing::Level::DEBUG, "cursor_append", "8", "error"),
+            ]
+        );
+
+        assert_eq!(captured.events.len(), 1);
+        assert_eq!(captured.events[0]["table"], "Rows");
+        assert_eq!(captured.events[0]["operation"], "cursor_append");
+    }
+}
@@ -1,68 +1,191 @@
-use rkyv::{Archive, ser::serializers::AllocSerializer};
-use std::str::FromStr;
+use rkyv::{
+    Archive, CheckBytes, ser::serializers::AllocSerializer,
+    validation::validators::DefaultValidator,
+};
+use std::{cell::RefCell, str::FromStr};
 
 use bytes::BufMut;
-use libmdbx_native::RW;
-use reth_db::{DatabaseError, TableType};
+use libmdbx_native::{DatabaseFlags, RW};
+use reth_db::{
+    DatabaseError, TableType,
+    table::{DupSort, Table},
+};
 
-use crate::implementation::LibmdbxTx;
+use crate::{implementation::LibmdbxTx, registry::TableRegistry};
 
 pub trait TableSet: Send + Sync + Sized + FromStr<Err = String> {
     const NUM_TABLES: usize;
 
     fn create_tables(txn: &LibmdbxTx<RW>) -> Result<(), DatabaseError>;
 
+    /// Registers the per-table behaviour of every table in the set.
+    fn register(registry: &mut TableRegistry);
+
     fn as_usize(&self) -> usize;
 }
 
 pub trait TableDet: reth_db::table::Table {
     fn table_type(&self) -> TableType;
+
+    /// Flags the table is created with, following [`TableDet::table_type`] by default.
+    fn database_flags(&self) -> DatabaseFlags {
+        match self.table_type() {
+            TableType::Table => DatabaseFlags::default(),
+            TableType::DupSort => DatabaseFlags::DUP_SORT,
+        }
+    }
+
+    /// Registers what the transactions need to maintain for this table. Registers a
+    /// [`Debug`](std::fmt::Debug) decoder by default.
+    fn register(registry: &mut TableRegistry) {
+        registry.decoder::<Self>();
+    }
+}
+
+/// A DUPSORT table indexing the rows of [`SecondaryIndex::Primary`] by a key extracted from
+/// them. Each index key maps to the keys of the primary rows it was extracted from.
+///
+/// The table is declared with `db_table!((Index, index_of = Primary) | Key)`, leaving
+/// [`SecondaryIndex::extract`] to implement.
+///
+/// Once registered on the [`TableSet`], the index is kept in sync by
+/// [`DbTxMut::put`](reth_db::transaction::DbTxMut::put) and
+/// [`DbTxMut::delete`](reth_db::transaction::DbTxMut::delete). Writes made through cursors are
+/// not tracked.
+///
+/// When the primary table is DUPSORT, the index maps to the primary key shared by its duplicates:
+/// the entry is kept until no duplicate extracts its index key anymore, and
+/// [`LibmdbxTx::get_by_index`] returns every duplicate extracting the looked up key.
+pub trait SecondaryIndex: DupSort {
+    /// The table being indexed.
+    type Primary: Table;
+
+    /// Returns the index key of a primary row, or [None] if the row is not indexed.
+    fn extract(
+        key: &<Self::Primary as Table>::Key,
+        value: &<Self::Primary as Table>::Value,
+    ) -> Option<Self::Key>;
+}
+
+/// Error of a codec of the wrapped traits, serialization and compression can't report their
+/// failures through [`Compress`](reth_db::table::Compress) so they are [`DatabaseError::Other`].
+fn codec_error(stage: &str, error: impl std::fmt::Display) -> DatabaseError {
+    DatabaseError::Other(format!("failed to {stage} value: {error}"))
+}
+
+thread_local! {
+    static CODEC_ERROR: RefCell<Option<DatabaseError>> = const { RefCell::new(None) };
+}
+
+/// Reports a failure of an infallible encoding trait. The first failure is kept until the
+/// transaction writing the row takes it and fails the write.
+#[doc(hidden)]
+pub fn report_codec_error(stage: &str, error: impl std::fmt::Display) {
+    keep_codec_error(codec_error(stage, error));
+}
+
+fn keep_codec_error(error: DatabaseError) {
+    CODEC_ERROR.with_borrow_mut(|slot| {
+        slot.get_or_insert(error);
+    });
+}
+
+/// Takes the failure reported by the codecs since the last call.
+pub(crate) fn take_codec_error() -> Option<DatabaseError> {
+    CODEC_ERROR.take()
+}
+
+/// Validates the archived bytes of a `T` read from the database and deserializes them.
+#[doc(hidden)]
+pub fn deserialize_archived<T>(buf: &[u8]) -> alloy_rlp::Result<T>
+where
+    T: Archive,
+    T::Archived: for<'a> CheckBytes<DefaultValidator<'a>> + rkyv::Deserialize<T, rkyv::Infallible>,
+{
+    // Values aren't stored aligned to their archived type.
+    let mut aligned = rkyv::AlignedVec::with_capacity(buf.len());
+    aligned.extend_from_slice(buf);
+
+    let archived = rkyv::check_archived_root::<T>(&aligned)
+        .map_err(|_| alloy_rlp::Error::Custom("invalid archived value"))?;
+    let Ok(value) = rkyv::Deserialize::<T, _>::deserialize(archived, &mut rkyv::Infallible);
+
+    Ok(value)
 }
 
 pub trait WrapEncodable: rkyv::Serialize<AllocSerializer<256>> + Sized {
-    fn encode_wrapped(&self, out: &mut dyn BufMut) {
-        let encoded = rkyv::to_bytes(self).unwrap();
+    /// Serializes `self` with rkyv into `out`.
+    fn try_encode_wrapped(&self, out: &mut dyn BufMut) -> Result<(), DatabaseError> {
+        let encoded = rkyv::to_bytes(self).map_err(|e| codec_error("serialize", e))?;
 
         out.put_slice(&encoded);
+        Ok(())
+    }
+
+    /// Infallible form of [`WrapEncodable::try_encode_wrapped`] required by the encoding traits.
+    ///
+    /// A serialization failure is reported with [`report_codec_error`], which
+    /// [`DbTxMut::put`](reth_db::transaction::DbTxMut::put) and the cursor writes return as a
+    /// write error on the table.
+    fn encode_wrapped(&self, out: &mut dyn BufMut) {
+        if let Err(e) = self.try_encode_wrapped(out) {
+            keep_codec_error(e);
+        }
     }
 }
 
 pub trait WrapDecodable
 where
     Self: Archive + Sized,
-    <Self as Archive>::Archived: rkyv::Deserialize<Self, rkyv::Infallible>,
+    <Self as Archive>::Archived:
+        for<'a> CheckBytes<DefaultValidator<'a>> + rkyv::Deserialize<Self, rkyv::Infallible>,
 {
     fn decode_wrapped(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
-        let archived = unsafe { rkyv::archived_root::<Self>(&buf[..]) };
+        if buf.len() < std::mem::size_of::<<Self as Archive>::Archived>() {
+            return Err(alloy_rlp::Error::InputTooShort);
+        }
 
-        Ok(rkyv::Deserialize::<Self, _>::deserialize(archived, &mut rkyv::Infallible).unwrap())
+        deserialize_archived(buf)
     }
 }
 
 pub trait WrapCompress: WrapEncodable {
     type Compressed;
 
-    fn compress_to_buf_wrapped<B: alloy_primitives::bytes::BufMut + AsMut<[u8]>>(
+    /// Serializes `self` and compresses it with zstd into `buf`.
+    fn try_compress_to_buf_wrapped<B: alloy_primitives::bytes::BufMut + AsMut<[u8]>>(
         &self,
         buf: &mut B,
-    ) {
+    ) -> Result<(), DatabaseError> {
         let mut encoded = Vec::new();
-        WrapEncodable::encode_wrapped(self, &mut encoded);
-        let encoded_compressed = zstd::encode_all(&*encoded, 0).unwrap();
+        WrapEncodable::try_encode_wrapped(self, &mut encoded)?;
+        let encoded_compressed =
+            zstd::encode_all(&*encoded, 0).map_err(|e| codec_error("compress", e))?;
 
         buf.put_slice(&encoded_compressed);
+        Ok(())
+    }
+
+    /// Infallible form of [`WrapCompress::try_compress_to_buf_wrapped`], reports its failure like
+    /// [`WrapEncodable::encode_wrapped`].
+    fn compress_to_buf_wrapped<B: alloy_primitives::bytes::BufMut + AsMut<[u8]>>(
+        &self,
+        buf: &mut B,
+    ) {
+        if let Err(e) = self.try_compress_to_buf_wrapped(buf) {
+            keep_codec_error(e);
+        }
     }
 }
 
 pub trait WrapDecompress: WrapDecodable
 where
     Self: Archive + Sized,
-    <Self as Archive>::Archived: rkyv::Deserialize<Self, rkyv::Infallible>,
+    <Self as Archive>::Archived:
+        for<'a> CheckBytes<DefaultValidator<'a>> + rkyv::Deserialize<Self, rkyv::Infallible>,
 {
     fn decompress_wrapped(value: &[u8]) -> Result<Self, DatabaseError> {
-        let binding = value.to_vec();
-
-        let encoded_decompressed = zstd::decode_all(&*binding).unwrap();
+        let encoded_decompressed = zstd::decode_all(value).map_err(|_| DatabaseError::Decode)?;
         let buf = &mut encoded_decompressed.as_slice();
 
         Self::decode_wrapped(buf).map_err(|_| DatabaseError::Decode)
@@ -74,18 +197,29 @@ where
     Self: WrapEncodable + Sized,
     <Self as Archive>::Archived: rkyv::Deserialize<Self, rkyv::Infallible>,
 {
-    fn encode_key_wrapped(self) -> Vec<u8> {
+    /// Serializes the key with rkyv.
+    fn try_encode_key_wrapped(self) -> Result<Vec<u8>, DatabaseError> {
         let mut buf = bytes::BytesMut::new();
-        WrapEncodable::encode_wrapped(&self, &mut buf);
+        WrapEncodable::try_encode_wrapped(&self, &mut buf)?;
 
-        buf.to_vec()
+        Ok(buf.to_vec())
+    }
+
+    /// Infallible form of [`WrapEncode::try_encode_key_wrapped`], reports its failure like
+    /// [`WrapEncodable::encode_wrapped`] and returns an empty key.
+    fn encode_key_wrapped(self) -> Vec<u8> {
+        self.try_encode_key_wrapped().unwrap_or_else(|e| {
+            keep_codec_error(e);
+            Vec::new()
+        })
     }
 }
 
 pub trait WrapDecode
 where
     Self: WrapDecodable + Archive + Sized,
-    <Self as Archive>::Archived: rkyv::Deserialize<Self, rkyv::Infallible>,
+    <Self as Archive>::Archived:
+        for<'a> CheckBytes<DefaultValidator<'a>> + rkyv::Deserialize<Self, rkyv::Infallible>,
 {
     fn decode_wrapped_key(mut value: &[u8]) -> Result<Self, DatabaseError> {
         WrapDecodable::decode_wrapped(&mut value).map_err(|_| DatabaseError::Decode)
@@ -51,6 +51,7 @@ db_table!((EmptyStrategyTable) | u8, Thing);
     serde::Serialize,
     serde::Deserialize,
 )]
+#[archive(check_bytes)]
 // #[libmdbx_bindings::derive_libmdbx_value]
 // #[derive(Debug)]
 pub struct Thing {